@@ -0,0 +1,29 @@
+/*
+ * Timing comparisons for the ignored `bench_*` tests.
+ *
+ * Run them with `cargo test --release -- --ignored --nocapture`.
+ */
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// Run a baseline and a candidate implementation once each, check that they agree,
+/// and print how long each took
+pub(crate) fn compare<T: Debug + PartialEq>(
+    label: &str,
+    (baseline_name, baseline): (&str, impl FnOnce() -> T),
+    (candidate_name, candidate): (&str, impl FnOnce() -> T),
+) {
+    let (expected, baseline_time) = time(baseline);
+    let (actual, candidate_time) = time(candidate);
+    assert_eq!(actual, expected);
+    println!(
+        "{}: {} {:?}, {} {:?}",
+        label, baseline_name, baseline_time, candidate_name, candidate_time
+    )
+}
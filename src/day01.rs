@@ -1,4 +1,8 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::BufRead;
+use std::ops::Range;
+use std::str::FromStr;
 
 #[aoc_generator(day1)]
 fn parse_input(data: &str) -> Vec<u16> {
@@ -38,6 +42,286 @@ fn part2(data: &[u16]) -> usize {
         .count()
 }
 
+/// Why a line of a log couldn't be read as a reading
+#[derive(Debug)]
+pub enum ReadErrorKind {
+    Io(std::io::Error),
+    // The offending text
+    Parse(String),
+}
+
+#[derive(Debug)]
+pub struct ReadError {
+    // Starting from 1
+    pub line: usize,
+    pub kind: ReadErrorKind,
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.kind {
+            ReadErrorKind::Io(error) => write!(f, "Line {}: {}", self.line, error),
+            ReadErrorKind::Parse(text) => {
+                write!(f, "Line {}: invalid reading {:?}", self.line, text)
+            }
+        }
+    }
+}
+
+/// Lazily parse one reading per line, so that arbitrarily large logs can be
+/// processed without loading them in memory.
+/// Blank lines are skipped, and a bad line yields an error without ending the stream.
+pub fn read_readings<T: FromStr>(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<T, ReadError>> {
+    reader.lines().enumerate().filter_map(|(index, line)| {
+        let error = |kind| ReadError {
+            line: index + 1,
+            kind,
+        };
+        match line {
+            Err(e) => Some(Err(error(ReadErrorKind::Io(e)))),
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(
+                line.trim()
+                    .parse()
+                    .map_err(|_| error(ReadErrorKind::Parse(line.trim().to_string()))),
+            ),
+        }
+    })
+}
+
+/// Count how many times the sum of `window_size` consecutive readings is larger
+/// than the sum of the previous window.
+/// Same trick as in part2: consecutive windows share all but one reading, so we
+/// only need to compare readings that are `window_size` apart. This means we
+/// only ever keep `window_size` readings in memory.
+pub fn count_window_increases<T: PartialOrd>(
+    readings: impl IntoIterator<Item = T>,
+    window_size: usize,
+) -> usize {
+    assert!(window_size > 0, "Windows must contain at least one reading");
+    let mut window = VecDeque::with_capacity(window_size);
+    let mut count = 0;
+    for reading in readings {
+        if window.len() == window_size && reading > window.pop_front().unwrap() {
+            count += 1;
+        }
+        window.push_back(reading);
+    }
+    count
+}
+
+/// Smoothing filters for noisy readings.
+/// Filters are applied lazily to a stream of readings, so they can be chained
+/// with each other and fed to `count_window_increases`.
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    // Median of each window of `width` consecutive readings
+    MovingMedian(usize),
+    // Mean of each window of `width` consecutive readings
+    MovingAverage(usize),
+    // Drop readings further than `threshold` standard deviations away from the
+    // mean of the previous `width` readings
+    ZScore { width: usize, threshold: f64 },
+}
+
+impl Filter {
+    fn width(&self) -> usize {
+        match self {
+            Self::MovingMedian(width) | Self::MovingAverage(width) => *width,
+            Self::ZScore { width, .. } => *width,
+        }
+    }
+    pub fn apply<T: Into<f64>>(
+        self,
+        readings: impl IntoIterator<Item = T>,
+    ) -> impl Iterator<Item = f64> {
+        assert!(
+            self.width() > 0,
+            "Filters need a window of at least one reading"
+        );
+        Filtered {
+            filter: self,
+            readings: readings.into_iter().map(Into::into),
+            window: VecDeque::with_capacity(self.width()),
+        }
+    }
+}
+
+fn mean(window: &VecDeque<f64>) -> f64 {
+    window.iter().sum::<f64>() / window.len() as f64
+}
+
+fn median(window: &VecDeque<f64>) -> f64 {
+    let mut sorted = window.iter().copied().collect::<Vec<_>>();
+    sorted.sort_unstable_by(|l, r| l.partial_cmp(r).unwrap());
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[middle - 1] + sorted[middle]) / 2.
+    } else {
+        sorted[middle]
+    }
+}
+
+fn standard_deviation(window: &VecDeque<f64>, mean: f64) -> f64 {
+    (window.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / window.len() as f64).sqrt()
+}
+
+struct Filtered<I> {
+    filter: Filter,
+    readings: I,
+    window: VecDeque<f64>,
+}
+
+impl<I: Iterator<Item = f64>> Iterator for Filtered<I> {
+    type Item = f64;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reading = self.readings.next()?;
+            match self.filter {
+                Filter::MovingMedian(width) | Filter::MovingAverage(width) => {
+                    if self.window.len() == width {
+                        self.window.pop_front();
+                    }
+                    self.window.push_back(reading);
+                    if self.window.len() == width {
+                        return Some(match self.filter {
+                            Filter::MovingMedian(_) => median(&self.window),
+                            _ => mean(&self.window),
+                        });
+                    }
+                }
+                Filter::ZScore { width, threshold } => {
+                    let mut outlier = false;
+                    if self.window.len() == width {
+                        let mean = mean(&self.window);
+                        let standard_deviation = standard_deviation(&self.window, mean);
+                        // A flat window would reject anything that isn't exactly equal to it
+                        outlier = standard_deviation > 0.
+                            && (reading - mean).abs() > threshold * standard_deviation;
+                        self.window.pop_front();
+                    }
+                    // Outliers stay in the window so that we follow real changes of level
+                    self.window.push_back(reading);
+                    if !outlier {
+                        return Some(reading);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[aoc(day1, part2, Streaming)]
+fn part2_streaming(data: &[u16]) -> usize {
+    count_window_increases(data.iter().copied(), 3)
+}
+
+/// Maximal ranges of at least two readings where each reading satisfies `pred`
+/// when compared with the previous one
+fn runs(data: &[u16], pred: impl Fn(u16, u16) -> bool) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for index in 1..=data.len() {
+        if index == data.len() || !pred(data[index - 1], data[index]) {
+            if index - start > 1 {
+                runs.push(start..index);
+            }
+            start = index;
+        }
+    }
+    runs
+}
+
+fn longest_run(data: &[u16], pred: impl Fn(u16, u16) -> bool) -> Range<usize> {
+    runs(data, pred)
+        .into_iter()
+        // max_by_key keeps the last maximum: reverse to report the first one
+        .rev()
+        .max_by_key(|run| run.len())
+        // Any single reading is a run of length 1
+        .unwrap_or(0..data.len().min(1))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    // Index of the reading before the jump
+    pub index: usize,
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthReport {
+    pub n_readings: usize,
+    pub longest_increasing_run: Range<usize>,
+    pub longest_decreasing_run: Range<usize>,
+    pub plateaus: Vec<Range<usize>>,
+    pub largest_jump: Option<Jump>,
+    pub delta_histogram: BTreeMap<i32, usize>,
+}
+
+impl DepthReport {
+    pub fn from_readings(data: &[u16]) -> Self {
+        let deltas = data
+            .windows(2)
+            .map(|window| window[1] as i32 - window[0] as i32)
+            .collect::<Vec<_>>();
+        let largest_jump = deltas
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, delta)| delta.abs())
+            .map(|(index, &delta)| Jump { index, delta });
+        let delta_histogram = deltas.iter().fold(BTreeMap::new(), |mut histogram, delta| {
+            *histogram.entry(*delta).or_insert(0) += 1;
+            histogram
+        });
+        Self {
+            n_readings: data.len(),
+            longest_increasing_run: longest_run(data, |previous, next| next > previous),
+            longest_decreasing_run: longest_run(data, |previous, next| next < previous),
+            plateaus: runs(data, |previous, next| next == previous),
+            largest_jump,
+            delta_histogram,
+        }
+    }
+}
+
+impl std::fmt::Display for DepthReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Readings: {}", self.n_readings)?;
+        writeln!(
+            f,
+            "Longest increasing run: {} readings at {:?}",
+            self.longest_increasing_run.len(),
+            self.longest_increasing_run
+        )?;
+        writeln!(
+            f,
+            "Longest decreasing run: {} readings at {:?}",
+            self.longest_decreasing_run.len(),
+            self.longest_decreasing_run
+        )?;
+        writeln!(f, "Plateaus: {:?}", self.plateaus)?;
+        match self.largest_jump {
+            Some(jump) => writeln!(
+                f,
+                "Largest jump: {:+} from index {} to {}",
+                jump.delta,
+                jump.index,
+                jump.index + 1
+            )?,
+            None => writeln!(f, "Largest jump: none")?,
+        }
+        write!(f, "Deltas:")?;
+        for (delta, count) in &self.delta_histogram {
+            write!(f, "\n{:>+6}: {}", delta, count)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     fn input() -> Vec<u16> {
@@ -47,6 +331,7 @@ mod tests {
         vec![199, 200, 208, 210, 200, 207, 240, 269, 260, 263]
     }
     use super::*;
+    use crate::snapshot::assert_snapshot;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 7)
@@ -71,4 +356,115 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 1158)
     }
+    #[test]
+    fn test_part2_streaming_given_example_input() {
+        assert_eq!(part2_streaming(&example_input()), 5)
+    }
+    #[test]
+    fn test_part2_streaming() {
+        assert_eq!(part2_streaming(&input()), 1158)
+    }
+    #[test]
+    fn test_count_window_increases_given_any_window_size() {
+        let data = input();
+        assert_eq!(count_window_increases(data.iter(), 1), part1(&data));
+        for window_size in 1..10 {
+            let sums = data
+                .windows(window_size)
+                .map(|window| window.iter().map(|x| *x as usize).sum::<usize>())
+                .collect::<Vec<_>>();
+            let naive = sums.windows(2).filter(|pair| pair[1] > pair[0]).count();
+            assert_eq!(count_window_increases(data.iter(), window_size), naive)
+        }
+    }
+    #[test]
+    fn test_depth_report_given_example_input() {
+        let report = DepthReport::from_readings(&example_input());
+        assert_eq!(report.longest_increasing_run, 0..4);
+        assert_eq!(report.longest_decreasing_run, 3..5);
+        assert!(report.plateaus.is_empty());
+        assert_eq!(
+            report.largest_jump,
+            Some(Jump {
+                index: 5,
+                delta: 33
+            })
+        );
+        assert_eq!(report.delta_histogram.values().sum::<usize>(), 9);
+        assert_snapshot("day01_depth_report_example", &report.to_string())
+    }
+    #[test]
+    fn test_depth_report_plateaus() {
+        let report = DepthReport::from_readings(&[3, 3, 3, 2, 2, 1]);
+        assert_eq!(report.plateaus, vec![0..3, 3..5]);
+        assert_eq!(report.longest_increasing_run, 0..1);
+        assert_eq!(report.longest_decreasing_run, 2..4);
+        assert_eq!(report.delta_histogram.get(&0), Some(&3))
+    }
+    #[test]
+    fn test_moving_average() {
+        // Averaging over 3 readings preserves the order of the sums in part 2
+        let averages = Filter::MovingAverage(3).apply(input());
+        assert_eq!(count_window_increases(averages, 1), 1158)
+    }
+    #[test]
+    fn test_moving_median() {
+        let medians = Filter::MovingMedian(3).apply([1u16, 100, 2, 3, 4]);
+        assert_eq!(medians.collect::<Vec<_>>(), vec![2., 3., 3.]);
+        let medians = Filter::MovingMedian(2).apply([1u16, 2, 4]);
+        assert_eq!(medians.collect::<Vec<_>>(), vec![1.5, 3.])
+    }
+    #[test]
+    fn test_z_score() {
+        let filter = Filter::ZScore {
+            width: 4,
+            threshold: 3.,
+        };
+        let kept = filter.apply([10u16, 11, 10, 11, 500, 12]);
+        assert_eq!(kept.collect::<Vec<_>>(), vec![10., 11., 10., 11., 12.])
+    }
+    #[test]
+    fn test_filters_compose_with_window_increases_given_example_input() {
+        let medians = Filter::MovingMedian(5).apply(example_input());
+        assert_eq!(count_window_increases(medians, 1), 5);
+        let filter = Filter::ZScore {
+            width: 3,
+            threshold: 2.,
+        };
+        // 240 and 269 are too steep compared to the previous readings
+        let kept = filter.apply(example_input()).collect::<Vec<_>>();
+        assert_eq!(kept, vec![199., 200., 208., 210., 200., 207., 260., 263.]);
+        // 200, 208, 208, 207, 207, 260
+        let smoothed = Filter::MovingMedian(3).apply(kept);
+        assert_eq!(count_window_increases(smoothed, 2), 2)
+    }
+    #[test]
+    fn test_count_window_increases_given_reader() {
+        let reader = include_str!("../input/2021/day1.txt").as_bytes();
+        assert_eq!(
+            count_window_increases(read_readings::<u32>(reader).map(Result::unwrap), 3),
+            1158
+        )
+    }
+    #[test]
+    fn test_read_readings_given_bad_lines() {
+        let reader = "199\n200\n\n2O8\n  210 \n-3\n".as_bytes();
+        let readings = read_readings::<u16>(reader)
+            .map(|reading| reading.map_err(|e| e.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            readings,
+            vec![
+                Ok(199),
+                Ok(200),
+                Err("Line 4: invalid reading \"2O8\"".to_string()),
+                Ok(210),
+                Err("Line 6: invalid reading \"-3\"".to_string()),
+            ]
+        );
+        // Bad lines can be skipped without ending the stream
+        let reader = "199\n200\nx\n208\n".as_bytes();
+        let readings = read_readings::<u16>(reader).filter_map(Result::ok);
+        assert_eq!(count_window_increases(readings, 1), 2)
+    }
 }
@@ -2,15 +2,18 @@ use std::str::FromStr;
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
-enum Command {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
     Forward,
     Down,
     Up,
+    Back,
 }
 
-struct Move {
-    command: Command,
-    value: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub command: Command,
+    pub value: usize,
 }
 
 impl FromStr for Move {
@@ -23,6 +26,7 @@ impl FromStr for Move {
             "forward" => Command::Forward,
             "down" => Command::Down,
             "up" => Command::Up,
+            "back" => Command::Back,
             _ => {
                 return Err("Unexpected command");
             }
@@ -40,104 +44,633 @@ fn parse_input(data: &str) -> Vec<Move> {
         .unwrap()
 }
 
-mod part1 {
+/// What to do when a move would bring the submarine above the surface
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DepthPolicy {
+    #[default]
+    Error,
+    ClampToSurface,
+    Allow,
+}
+
+impl DepthPolicy {
+    pub fn check(self, depth: i64) -> Result<i64, SurfacedError> {
+        match self {
+            _ if depth >= 0 => Ok(depth),
+            Self::Error => Err(SurfacedError { depth }),
+            Self::ClampToSurface => Ok(0),
+            Self::Allow => Ok(depth),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfacedError {
+    // The depth the move would have reached
+    pub depth: i64,
+}
+
+impl std::fmt::Display for SurfacedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "the submarine would be above the surface, at depth {}",
+            self.depth
+        )
+    }
+}
+
+/// A move that a model couldn't apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CourseError<E> {
+    // Index of the move in the course
+    pub move_index: usize,
+    pub error: E,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for CourseError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Move {}: {}", self.move_index, self.error)
+    }
+}
+
+/// How a submarine moves in response to the commands of a course.
+/// Any model can be run over a course with `follow_course`, or with
+/// `trajectory` to see where it went.
+pub trait SubmarineModel: Clone {
+    // Why a move can't be applied, e.g. `SurfacedError` for the puzzle's models,
+    // depending on their `DepthPolicy`
+    type Error;
+    fn apply_move(&mut self, m: &Move) -> Result<(), Self::Error>;
+    // The puzzle's answer: depth multiplied by horizontal position
+    fn pretty(&self) -> i64;
+    // Names of the values describing the model's state, as written in trajectories.
+    // The first two are the horizontal position and the depth, which are drawn.
+    fn columns() -> &'static [&'static str];
+    // One value per column, in the same order
+    fn values(&self) -> Vec<i64>;
+}
+
+/// Position after each move, until the first error
+pub fn trajectory<'a, M: SubmarineModel + 'a>(
+    mut model: M,
+    moves: impl IntoIterator<Item = &'a Move> + 'a,
+) -> impl Iterator<Item = Result<M, CourseError<M::Error>>> + 'a {
+    let mut failed = false;
+    moves
+        .into_iter()
+        .enumerate()
+        .map_while(move |(move_index, m)| {
+            if failed {
+                return None;
+            }
+            let result = model
+                .apply_move(m)
+                .map(|_| model.clone())
+                .map_err(|error| CourseError { move_index, error });
+            failed = result.is_err();
+            Some(result)
+        })
+}
+
+/// Position after all the moves, or the first error
+pub fn follow_course<'a, M: SubmarineModel>(
+    mut model: M,
+    moves: impl IntoIterator<Item = &'a Move>,
+) -> Result<M, CourseError<M::Error>> {
+    for (move_index, m) in moves.into_iter().enumerate() {
+        model
+            .apply_move(m)
+            .map_err(|error| CourseError { move_index, error })?;
+    }
+    Ok(model)
+}
+
+pub mod part1 {
     use super::*;
 
-    #[derive(Default, Debug)]
-    pub(super) struct Position {
-        pub(super) depth: usize,
-        pub(super) horizontal: usize,
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Position {
+        pub depth: i64,
+        pub horizontal: i64,
+        pub policy: DepthPolicy,
     }
 
     impl Position {
-        pub(super) fn apply_move(&mut self, m: &Move) {
+        pub fn with_policy(policy: DepthPolicy) -> Self {
+            Self {
+                policy,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl SubmarineModel for Position {
+        type Error = SurfacedError;
+        fn apply_move(&mut self, m: &Move) -> Result<(), SurfacedError> {
+            let value = m.value as i64;
             match m.command {
-                Command::Forward => self.horizontal += m.value,
-                Command::Down => self.depth += m.value,
-                Command::Up => self.depth -= m.value,
+                Command::Forward => self.horizontal += value,
+                Command::Back => self.horizontal -= value,
+                Command::Down => self.depth = self.policy.check(self.depth + value)?,
+                Command::Up => self.depth = self.policy.check(self.depth - value)?,
             }
+            Ok(())
         }
-        pub(super) fn pretty(&self) -> usize {
+        fn pretty(&self) -> i64 {
             self.depth * self.horizontal
         }
+        fn columns() -> &'static [&'static str] {
+            &["horizontal", "depth"]
+        }
+        fn values(&self) -> Vec<i64> {
+            vec![self.horizontal, self.depth]
+        }
     }
 }
 
 #[aoc(day2, part1, Naive)]
-fn part1_naive(data: &[Move]) -> usize {
-    data.iter()
-        .fold(part1::Position::default(), |mut pos, m| {
-            pos.apply_move(m);
-            pos
-        })
+fn part1_naive(data: &[Move]) -> i64 {
+    follow_course(part1::Position::default(), data)
+        .unwrap()
         .pretty()
 }
 
 /*
  * Optimization (~10% improvement):
  * For part 1, the order of the moves doesn't matter.
- * This means we care about 3 sums:
- *   total horizontal moves,
+ * This means we care about 4 sums:
+ *   total forward moves,
  *   total down moves,
  *   total up moves,
- * All of these fit in 16 bits, which means that we can combine the
- * 3 of them within one 64 bit integer to make the sum slightly
- * cheaper.
- * We can then unpack the 3 values and calculate the final position
+ *   total back moves,
+ * For the puzzle input, all of these fit in 16 bits, which means that we
+ * can combine the 4 of them within one 64 bit integer to make the sum
+ * slightly cheaper.
+ * We can then unpack the 4 values and calculate the final position
+ * Note that since we lose the order of the moves, we can't tell whether the
+ * submarine went above the surface on its way. Only when it ends above the
+ * surface do we know it did, and then we follow the moves in order to report
+ * where, like `part1_naive`.
+ *
+ * If a sum doesn't fit in 16 bits, it would silently bleed into the next one.
+ * No sum can exceed the number of moves times the largest value, so we keep
+ * track of the largest value while summing, and if that bound doesn't fit,
+ * we sum again without packing.
  */
-#[aoc(day2, part1, Unordered)]
-fn part1(data: &[Move]) -> usize {
-    let combined: usize = data
+const LANE_BITS: usize = 16;
+const LANE_MAX: usize = (1 << LANE_BITS) - 1;
+
+fn lanes_fit(n_moves: usize, max_value: usize) -> bool {
+    matches!(n_moves.checked_mul(max_value), Some(bound) if bound <= LANE_MAX)
+}
+
+fn unordered_answer(
+    data: &[Move],
+    horizontal: i64,
+    depth: i64,
+) -> Result<i64, CourseError<SurfacedError>> {
+    if depth < 0 {
+        return follow_course(part1::Position::default(), data).map(|position| position.pretty());
+    }
+    Ok(horizontal * depth)
+}
+
+fn part1_wide(data: &[Move]) -> Result<i64, CourseError<SurfacedError>> {
+    let (horizontal, down, up) = data.iter().fold((0i64, 0i64, 0i64), |(h, d, u), m| {
+        let value = m.value as i64;
+        match m.command {
+            Command::Forward => (h + value, d, u),
+            Command::Back => (h - value, d, u),
+            Command::Down => (h, d + value, u),
+            Command::Up => (h, d, u + value),
+        }
+    });
+    unordered_answer(data, horizontal, down - up)
+}
+
+fn part1_unordered(data: &[Move]) -> Result<i64, CourseError<SurfacedError>> {
+    let (combined, max_value) = data
         .iter()
-        .map(|m| match m.command {
-            Command::Forward => m.value,
-            Command::Down => m.value << 16,
-            Command::Up => m.value << 32,
-        })
-        .sum();
-    let horizontal = combined << 48 >> 48;
-    let down = combined << 32 >> 48;
-    let up = combined << 16 >> 48;
-    horizontal * (down - up)
+        .fold((0usize, 0usize), |(combined, max_value), m| {
+            let packed = match m.command {
+                Command::Forward => m.value,
+                Command::Down => m.value << LANE_BITS,
+                Command::Up => m.value << (2 * LANE_BITS),
+                Command::Back => m.value << (3 * LANE_BITS),
+            };
+            // Wrapping, as we'll discard the result anyway if it overflows
+            (combined.wrapping_add(packed), max_value.max(m.value))
+        });
+    if !lanes_fit(data.len(), max_value) {
+        return part1_wide(data);
+    }
+    let forward = (combined << 48 >> 48) as i64;
+    let down = (combined << 32 >> 48) as i64;
+    let up = (combined << 16 >> 48) as i64;
+    let back = (combined >> 48) as i64;
+    unordered_answer(data, forward - back, down - up)
 }
 
-mod part2 {
+#[aoc(day2, part1, Unordered)]
+fn part1(data: &[Move]) -> i64 {
+    part1_unordered(data).unwrap()
+}
+
+pub mod part2 {
     use super::*;
 
-    #[derive(Default, Debug)]
-    pub(super) struct Position {
-        pub(super) aim: usize,
-        pub(super) depth: usize,
-        pub(super) horizontal: usize,
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Position {
+        // A negative aim points towards the surface
+        pub aim: i64,
+        pub depth: i64,
+        pub horizontal: i64,
+        pub policy: DepthPolicy,
     }
 
     impl Position {
-        pub(super) fn apply_move(&mut self, m: &Move) {
+        pub fn with_policy(policy: DepthPolicy) -> Self {
+            Self {
+                policy,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl SubmarineModel for Position {
+        type Error = SurfacedError;
+        fn apply_move(&mut self, m: &Move) -> Result<(), SurfacedError> {
+            let value = m.value as i64;
             match m.command {
                 Command::Forward => {
-                    self.depth += self.aim * m.value;
-                    self.horizontal += m.value;
+                    self.depth = self.policy.check(self.depth + self.aim * value)?;
+                    self.horizontal += value;
+                }
+                // Move backwards along the current heading
+                Command::Back => {
+                    self.depth = self.policy.check(self.depth - self.aim * value)?;
+                    self.horizontal -= value;
                 }
-                Command::Down => self.aim += m.value,
-                Command::Up => self.aim -= m.value,
+                Command::Down => self.aim += value,
+                Command::Up => self.aim -= value,
             }
+            Ok(())
         }
-        pub(super) fn pretty(&self) -> usize {
+        fn pretty(&self) -> i64 {
             self.depth * self.horizontal
         }
+        fn columns() -> &'static [&'static str] {
+            &["horizontal", "depth", "aim"]
+        }
+        fn values(&self) -> Vec<i64> {
+            vec![self.horizontal, self.depth, self.aim]
+        }
+    }
+
+    /// The effect of a sequence of moves, as an affine transform:
+    ///   horizontal += H, aim += A, depth += D + aim * H
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Transform {
+        pub horizontal: i64,
+        pub aim: i64,
+        pub depth: i64,
+    }
+
+    impl Transform {
+        pub fn from_move(m: &Move) -> Self {
+            let value = m.value as i64;
+            match m.command {
+                Command::Forward => Self {
+                    horizontal: value,
+                    ..Default::default()
+                },
+                Command::Back => Self {
+                    horizontal: -value,
+                    ..Default::default()
+                },
+                Command::Down => Self {
+                    aim: value,
+                    ..Default::default()
+                },
+                Command::Up => Self {
+                    aim: -value,
+                    ..Default::default()
+                },
+            }
+        }
+        // Apply `self`, then `next`
+        pub fn then(self, next: Self) -> Self {
+            Self {
+                horizontal: self.horizontal + next.horizontal,
+                aim: self.aim + next.aim,
+                depth: self.depth + next.depth + self.aim * next.horizontal,
+            }
+        }
+        pub fn apply(self, pos: Position) -> Position {
+            Position {
+                horizontal: pos.horizontal + self.horizontal,
+                aim: pos.aim + self.aim,
+                depth: pos.depth + self.depth + pos.aim * self.horizontal,
+                ..pos
+            }
+        }
+    }
+}
+
+/*
+ * Extended course language, to write courses by hand.
+ * On top of one move per line, where the move's command and value can only be
+ * followed by the '}' closing blocks, it supports:
+ *   `#` comments until the end of the line,
+ *   blank lines,
+ *   `repeat N { ... }` blocks, which can be nested, with `repeat N {` on one line.
+ * For instance:
+ *   # Zigzag twice
+ *   repeat 2 {
+ *       down 2
+ *       forward 5
+ *       up 2  # back to the previous depth
+ *   }
+ */
+pub mod course {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Statement {
+        Move(Move),
+        Repeat { count: usize, body: Vec<Statement> },
+    }
+
+    impl Statement {
+        fn has_moves(&self) -> bool {
+            match self {
+                Self::Move(_) => true,
+                Self::Repeat { count, body } => *count > 0 && body.iter().any(Self::has_moves),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ParseError {
+        // One-based, like in text editors
+        pub line: usize,
+        pub message: &'static str,
+    }
+
+    impl std::fmt::Display for ParseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "Line {}: {}", self.line, self.message)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Course {
+        pub statements: Vec<Statement>,
+    }
+
+    // Words, except for braces which are always tokens on their own
+    fn tokenize(s: &str) -> Vec<(usize, String)> {
+        s.lines()
+            .enumerate()
+            .flat_map(|(line_index, line)| {
+                let code = line.split('#').next().unwrap();
+                code.replace('{', " { ")
+                    .replace('}', " } ")
+                    .split_whitespace()
+                    .map(|token| (line_index + 1, token.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    type Tokens = std::iter::Peekable<std::vec::IntoIter<(usize, String)>>;
+
+    // The next token, only if it's on the given line
+    fn next_on_line(tokens: &mut Tokens, line: usize) -> Option<String> {
+        tokens
+            .next_if(|(token_line, _)| *token_line == line)
+            .map(|(_, token)| token)
+    }
+
+    fn parse_block(
+        tokens: &mut Tokens,
+        opening_line: Option<usize>,
+    ) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = Vec::new();
+        while let Some((line, token)) = tokens.next() {
+            let error = |message| ParseError { line, message };
+            match token.as_str() {
+                "}" if opening_line.is_some() => return Ok(statements),
+                "}" => return Err(error("Unexpected '}'")),
+                "{" => return Err(error("Unexpected '{'")),
+                "repeat" => {
+                    let count = match next_on_line(tokens, line) {
+                        Some(count) => count
+                            .parse()
+                            .map_err(|_| error("Unexpected repeat count"))?,
+                        None => return Err(error("Expected a repeat count")),
+                    };
+                    if next_on_line(tokens, line).as_deref() != Some("{") {
+                        return Err(error("Expected '{' after the repeat count"));
+                    }
+                    let body = parse_block(tokens, Some(line))?;
+                    statements.push(Statement::Repeat { count, body });
+                }
+                command => {
+                    let value = match next_on_line(tokens, line) {
+                        Some(value) => value,
+                        None => return Err(error("Expected a value after the command")),
+                    };
+                    let rest_of_line = tokens.peek().filter(|(next_line, _)| *next_line == line);
+                    if rest_of_line.is_some_and(|(_, token)| token != "}") {
+                        return Err(error("Expected one move per line"));
+                    }
+                    let m = format!("{} {}", command, value).parse().map_err(error)?;
+                    statements.push(Statement::Move(m));
+                }
+            }
+        }
+        match opening_line {
+            Some(line) => Err(ParseError {
+                line,
+                message: "Unclosed '{'",
+            }),
+            None => Ok(statements),
+        }
+    }
+
+    impl FromStr for Course {
+        type Err = ParseError;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut tokens = tokenize(s).into_iter().peekable();
+            Ok(Self {
+                statements: parse_block(&mut tokens, None)?,
+            })
+        }
+    }
+
+    impl Course {
+        /// Unroll the course lazily, so that large repeat counts don't allocate
+        pub fn moves(&self) -> Moves<'_> {
+            Moves {
+                stack: vec![(&self.statements, 0, 1)],
+            }
+        }
+    }
+
+    pub struct Moves<'a> {
+        // Blocks being run, with the index of their next statement and how
+        // many times they still need to be run (including this one)
+        stack: Vec<(&'a [Statement], usize, usize)>,
+    }
+
+    impl<'a> Iterator for Moves<'a> {
+        type Item = &'a Move;
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let (block, index, remaining) = self.stack.last_mut()?;
+                let block: &'a [Statement] = block;
+                if *index == block.len() {
+                    if *remaining > 1 {
+                        *remaining -= 1;
+                        *index = 0;
+                    } else {
+                        self.stack.pop();
+                    }
+                    continue;
+                }
+                let statement = &block[*index];
+                *index += 1;
+                match statement {
+                    Statement::Move(m) => return Some(m),
+                    Statement::Repeat { count, body } => {
+                        if statement.has_moves() {
+                            self.stack.push((body, 0, *count))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub mod trajectory {
+    use super::SubmarineModel;
+    use std::io::Write;
+
+    /// One row per position, where step `n` is the position after `n` moves,
+    /// with the columns of the model
+    pub fn write_csv<M: SubmarineModel>(
+        positions: &[M],
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "step,{}", M::columns().join(","))?;
+        for (index, position) in positions.iter().enumerate() {
+            let values = position
+                .values()
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>();
+            writeln!(writer, "{},{}", index + 1, values.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// A polyline starting from the origin, with depth increasing downwards.
+    /// Depth usually grows much faster than the horizontal position, so the
+    /// drawing is stretched to fill the whole image.
+    pub fn write_svg<M: SubmarineModel>(
+        positions: &[M],
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let points = std::iter::once((0, 0))
+            .chain(positions.iter().map(|position| {
+                let values = position.values();
+                (values[0], values[1])
+            }))
+            .collect::<Vec<_>>();
+        let min_x = points.iter().map(|(x, _)| *x).min().unwrap();
+        let max_x = points.iter().map(|(x, _)| *x).max().unwrap();
+        let min_y = points.iter().map(|(_, y)| *y).min().unwrap();
+        let max_y = points.iter().map(|(_, y)| *y).max().unwrap();
+        writeln!(
+            writer,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"800\" height=\"400\" viewBox=\"{} {} {} {}\" preserveAspectRatio=\"none\">",
+            min_x,
+            min_y,
+            (max_x - min_x).max(1),
+            (max_y - min_y).max(1)
+        )?;
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<_>>();
+        writeln!(
+            writer,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"black\" vector-effect=\"non-scaling-stroke\"/>",
+            points.join(" ")
+        )?;
+        writeln!(writer, "</svg>")
     }
 }
 
 #[aoc(day2, part2)]
-fn part2(data: &[Move]) -> usize {
-    data.iter()
-        .fold(part2::Position::default(), |mut pos, m| {
-            pos.apply_move(m);
-            pos
-        })
+fn part2(data: &[Move]) -> i64 {
+    follow_course(part2::Position::default(), data)
+        .unwrap()
         .pretty()
 }
 
+/*
+ * Parallel part 2:
+ * In part 2, the order of the moves matters, but each move is an affine
+ * transform of the position (see `part2::Transform`).
+ * Applying (H1, A1, D1) then (H2, A2, D2) gives
+ *   (H1 + H2, A1 + A2, D1 + D2 + A1 * H2)
+ * which is associative, so we can compose chunks of moves on separate
+ * threads, then combine the transforms of the chunks in order.
+ * Like for Unordered in part 1, we can't tell whether the submarine went
+ * above the surface on its way.
+ * Spawning threads costs much more than following a short course, so each
+ * thread gets at least `MIN_MOVES_PER_THREAD` moves.
+ */
+const MIN_MOVES_PER_THREAD: usize = 1 << 16;
+
+fn compose(moves: &[Move]) -> part2::Transform {
+    moves
+        .iter()
+        .map(part2::Transform::from_move)
+        .fold(part2::Transform::default(), part2::Transform::then)
+}
+
+fn compose_in_parallel(data: &[Move], n_threads: usize) -> part2::Transform {
+    if n_threads <= 1 {
+        return compose(data);
+    }
+    let chunk_size = data.len().div_ceil(n_threads);
+    std::thread::scope(|scope| {
+        data.chunks(chunk_size.max(1))
+            .map(|chunk| scope.spawn(move || compose(chunk)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(part2::Transform::default(), part2::Transform::then)
+    })
+}
+
+fn part2_parallel_position(data: &[Move]) -> part2::Position {
+    let n_threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(data.len() / MIN_MOVES_PER_THREAD);
+    compose_in_parallel(data, n_threads).apply(part2::Position::default())
+}
+
+#[aoc(day2, part2, Parallel)]
+fn part2_parallel(data: &[Move]) -> i64 {
+    part2_parallel_position(data).pretty()
+}
+
 #[cfg(test)]
 mod tests {
     fn input() -> Vec<Move> {
@@ -153,6 +686,8 @@ forward 2";
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::random::Random;
+    use crate::snapshot::assert_snapshot;
     #[test]
     fn test_part1_naive_given_example_input() {
         assert_eq!(part1_naive(&example_input()), 150)
@@ -177,4 +712,419 @@ forward 2";
     fn test_part2() {
         assert_eq!(part2(&input()), 1741971043)
     }
+    #[test]
+    fn test_part2_parallel_given_example_input() {
+        assert_eq!(part2_parallel(&example_input()), 900)
+    }
+    #[test]
+    fn test_part2_parallel() {
+        assert_eq!(part2_parallel(&input()), 1741971043)
+    }
+    #[test]
+    fn test_transform_is_associative() {
+        let transforms = parse_input("forward 3\ndown 2\nback 1\nup 5\nforward 4")
+            .iter()
+            .map(part2::Transform::from_move)
+            .collect::<Vec<_>>();
+        for split in 0..transforms.len() {
+            let (l, r) = transforms.split_at(split);
+            let l = l
+                .iter()
+                .fold(part2::Transform::default(), |t, m| t.then(*m));
+            let r = r
+                .iter()
+                .fold(part2::Transform::default(), |t, m| t.then(*m));
+            let all = transforms
+                .iter()
+                .fold(part2::Transform::default(), |t, m| t.then(*m));
+            assert_eq!(l.then(r), all)
+        }
+    }
+    #[test]
+    fn test_part2_parallel_given_generated_courses() {
+        for (seed, n_moves) in [(6, 10), (7, (1 << 16) + 1), (8, 1_000_000)] {
+            let data = generate_course(seed, n_moves, 9);
+            let sequential = follow_course(part2::Position::default(), &data);
+            assert_eq!(Ok(part2_parallel_position(&data)), sequential);
+            // Independently of the number of cores of the machine running the tests
+            for n_threads in 2..=7 {
+                let position =
+                    compose_in_parallel(&data, n_threads).apply(part2::Position::default());
+                assert_eq!(Ok(position), sequential)
+            }
+        }
+    }
+    #[test]
+    #[ignore]
+    fn bench_part2_parallel_given_generated_course() {
+        let data = generate_course(9, 20_000_000, 9);
+        crate::bench::compare(
+            &format!("{} moves", data.len()),
+            ("sequential", || {
+                follow_course(part2::Position::default(), &data)
+            }),
+            ("parallel", || Ok(part2_parallel_position(&data))),
+        )
+    }
+    // Pseudo random course that never goes above the surface
+    fn generate_course(seed: u64, n_moves: usize, max_value: usize) -> Vec<Move> {
+        let mut random = Random::new(seed);
+        let mut next = move |n: usize| random.below(n as u64) as usize;
+        let mut depth = 0;
+        (0..n_moves)
+            .map(|_| {
+                let value = 1 + next(max_value);
+                let command = match next(3) {
+                    0 => Command::Forward,
+                    1 => Command::Down,
+                    _ if value <= depth => Command::Up,
+                    _ => Command::Down,
+                };
+                match command {
+                    Command::Down => depth += value,
+                    Command::Up => depth -= value,
+                    Command::Forward | Command::Back => {}
+                }
+                Move { command, value }
+            })
+            .collect()
+    }
+    #[test]
+    fn test_part1_given_lane_overflow() {
+        // Each sum fits, but the horizontal one would bleed into the down one
+        let data = parse_input("forward 65535\nforward 1\ndown 2");
+        assert_eq!(part1(&data), 131072);
+        assert_eq!(part1(&data), part1_naive(&data))
+    }
+    #[test]
+    fn test_part1_given_generated_courses() {
+        for (seed, n_moves, max_value) in [
+            (1, 1000, 9),
+            (2, 7281, 9),
+            (3, 100_000, 9),
+            (4, 100_000, 1000),
+            (5, 1_000_000, 1000),
+        ] {
+            let data = generate_course(seed, n_moves, max_value);
+            assert_eq!(part1(&data), part1_naive(&data))
+        }
+    }
+    #[test]
+    fn test_course_given_example_input() {
+        let course: course::Course = "# The example, in a roundabout way
+repeat 1 {
+    forward 5
+
+    down 5 # dive
+}
+forward 8
+up 3
+down 8
+repeat 2 {forward 1}
+"
+        .parse()
+        .unwrap();
+        let moves = course.moves().copied().collect::<Vec<_>>();
+        assert_eq!(part1_naive(&moves), part1_naive(&example_input()));
+        assert_eq!(
+            follow_course(part2::Position::default(), course.moves()),
+            follow_course(part2::Position::default(), &example_input())
+        )
+    }
+    #[test]
+    fn test_course_given_nested_repeats() {
+        let course: course::Course = "repeat 3 {
+    repeat 2 {
+        down 1
+        repeat 0 { up 5 } }
+    forward 2
+    back 1
+}
+repeat 1000000000 { repeat 0 { forward 1 } }
+repeat 0 {}"
+            .parse()
+            .unwrap();
+        assert_eq!(course.moves().count(), 12);
+        let position = follow_course(part1::Position::default(), course.moves()).unwrap();
+        assert_eq!((position.horizontal, position.depth), (3, 6));
+        let position = follow_course(part2::Position::default(), course.moves()).unwrap();
+        assert_eq!(
+            (position.horizontal, position.depth, position.aim),
+            (3, 2 + 4 + 6, 6)
+        )
+    }
+    #[test]
+    fn test_course_parse_errors() {
+        let error = |s: &str| s.parse::<course::Course>().unwrap_err();
+        assert_eq!(error("forward 1\nrepeat 2 {\nup 1").line, 2);
+        assert_eq!(error("forward 1\n}").message, "Unexpected '}'");
+        assert_eq!(
+            error("repeat x { up 1 }").message,
+            "Unexpected repeat count"
+        );
+        assert_eq!(error("down 1\nsideways 2").message, "Unexpected command");
+        assert_eq!(error("down 1\nforward").line, 2);
+        // A move's value is never taken from the next line
+        let missing_value = error("down 1\nforward\n5");
+        assert_eq!(
+            (missing_value.line, missing_value.message),
+            (2, "Expected a value after the command")
+        );
+        assert_eq!(
+            error("repeat\n2\n{\nup 1}").message,
+            "Expected a repeat count"
+        );
+        assert_eq!(
+            error("repeat 2\n{\nup 1}").message,
+            "Expected '{' after the repeat count"
+        );
+        let two_moves = error("down 1\nforward 5 up 1");
+        assert_eq!(
+            (two_moves.line, two_moves.message),
+            (2, "Expected one move per line")
+        )
+    }
+    fn example_positions() -> Vec<part2::Position> {
+        trajectory(part2::Position::default(), &example_input())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+    #[test]
+    fn test_trajectory_given_example_input() {
+        let depths = trajectory(part1::Position::default(), &example_input())
+            .map(|pos| pos.unwrap().depth)
+            .collect::<Vec<_>>();
+        assert_eq!(depths, vec![0, 5, 5, 2, 10, 10]);
+        let positions = example_positions();
+        assert_eq!(positions.len(), 6);
+        assert_eq!(positions.last().unwrap().values(), vec![15, 60, 10])
+    }
+    #[test]
+    fn test_trajectory_stops_at_first_error() {
+        let data = parse_input("forward 1\nup 1\nforward 1");
+        let steps = trajectory(part1::Position::default(), &data).collect::<Vec<_>>();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            steps[1],
+            Err(CourseError {
+                move_index: 1,
+                error: SurfacedError { depth: -1 }
+            })
+        )
+    }
+    #[test]
+    fn test_write_csv_given_example_input() {
+        let mut csv = Vec::new();
+        trajectory::write_csv(&example_positions(), &mut csv).unwrap();
+        assert_snapshot(
+            "day02_trajectory_example_csv",
+            &String::from_utf8(csv).unwrap(),
+        )
+    }
+    #[test]
+    fn test_write_svg_given_example_input() {
+        let mut svg = Vec::new();
+        trajectory::write_svg(&example_positions(), &mut svg).unwrap();
+        assert_snapshot(
+            "day02_trajectory_example_svg",
+            &String::from_utf8(svg).unwrap(),
+        )
+    }
+    // Like part 2, except that each forward move loses `drag` of the aim
+    #[derive(Debug, Default, Clone)]
+    struct DraggedAim {
+        aim: i64,
+        depth: i64,
+        horizontal: i64,
+        drag: i64,
+    }
+    impl SubmarineModel for DraggedAim {
+        type Error = SurfacedError;
+        fn apply_move(&mut self, m: &Move) -> Result<(), SurfacedError> {
+            let value = m.value as i64;
+            match m.command {
+                Command::Forward | Command::Back => {
+                    let value = if m.command == Command::Forward {
+                        value
+                    } else {
+                        -value
+                    };
+                    self.depth = DepthPolicy::Error.check(self.depth + self.aim * value)?;
+                    self.horizontal += value;
+                    self.aim -= self.aim.signum() * self.drag.min(self.aim.abs());
+                }
+                Command::Down => self.aim += value,
+                Command::Up => self.aim -= value,
+            }
+            Ok(())
+        }
+        fn pretty(&self) -> i64 {
+            self.depth * self.horizontal
+        }
+        fn columns() -> &'static [&'static str] {
+            &["horizontal", "depth", "aim"]
+        }
+        fn values(&self) -> Vec<i64> {
+            vec![self.horizontal, self.depth, self.aim]
+        }
+    }
+    #[test]
+    fn test_user_defined_model() {
+        let without_drag = follow_course(DraggedAim::default(), &input());
+        assert_eq!(without_drag.unwrap().pretty(), part2(&input()));
+        let with_drag = DraggedAim {
+            drag: 1,
+            ..Default::default()
+        };
+        let course: course::Course = EXAMPLE_INPUT_STR.parse().unwrap();
+        let end = follow_course(with_drag.clone(), course.moves()).unwrap();
+        assert_eq!((end.horizontal, end.depth, end.aim), (15, 58, 8));
+        let positions = trajectory(with_drag, course.moves())
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let mut csv = Vec::new();
+        trajectory::write_csv(&positions, &mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().ends_with("\n6,15,58,8\n"))
+    }
+    // Like part 1, also keeping track of the distance travelled, up to an optional range
+    #[derive(Debug, Default, Clone)]
+    struct Odometer {
+        position: part1::Position,
+        distance: i64,
+        range: Option<i64>,
+    }
+    #[derive(Debug, PartialEq, Eq)]
+    enum OdometerError {
+        Surfaced(SurfacedError),
+        OutOfRange { distance: i64 },
+    }
+    impl SubmarineModel for Odometer {
+        type Error = OdometerError;
+        fn apply_move(&mut self, m: &Move) -> Result<(), OdometerError> {
+            let distance = self.distance + m.value as i64;
+            if self.range.is_some_and(|range| distance > range) {
+                return Err(OdometerError::OutOfRange { distance });
+            }
+            self.position
+                .apply_move(m)
+                .map_err(OdometerError::Surfaced)?;
+            self.distance = distance;
+            Ok(())
+        }
+        fn pretty(&self) -> i64 {
+            self.position.pretty()
+        }
+        fn columns() -> &'static [&'static str] {
+            &["horizontal", "depth", "distance"]
+        }
+        fn values(&self) -> Vec<i64> {
+            vec![self.position.horizontal, self.position.depth, self.distance]
+        }
+    }
+    #[test]
+    fn test_trajectory_given_model_with_other_columns() {
+        let odometers = trajectory(Odometer::default(), &example_input())
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let mut csv = Vec::new();
+        trajectory::write_csv(&odometers, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("step,horizontal,depth,distance\n1,5,0,5\n"));
+        assert!(csv.ends_with("\n6,15,10,31\n"));
+        // Only the horizontal position and the depth are drawn
+        let positions = odometers
+            .iter()
+            .map(|odometer| odometer.position)
+            .collect::<Vec<_>>();
+        let (mut odometers_svg, mut positions_svg) = (Vec::new(), Vec::new());
+        trajectory::write_svg(&odometers, &mut odometers_svg).unwrap();
+        trajectory::write_svg(&positions, &mut positions_svg).unwrap();
+        assert_eq!(odometers_svg, positions_svg)
+    }
+    #[test]
+    fn test_user_defined_model_errors() {
+        let odometer = Odometer {
+            range: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            follow_course(odometer.clone(), &example_input()).unwrap_err(),
+            CourseError {
+                move_index: 3,
+                error: OdometerError::OutOfRange { distance: 21 }
+            }
+        );
+        assert_eq!(
+            follow_course(odometer, &parse_input("forward 1\nup 2")).unwrap_err(),
+            CourseError {
+                move_index: 1,
+                error: OdometerError::Surfaced(SurfacedError { depth: -2 })
+            }
+        )
+    }
+    #[test]
+    fn test_part1_given_back_moves() {
+        let data = parse_input("forward 5\ndown 2\nback 3\nforward 1");
+        assert_eq!(part1(&data), 6);
+        assert_eq!(part1_naive(&data), 6);
+        assert_eq!(part1_wide(&data), Ok(6))
+    }
+    #[test]
+    fn test_part1_surfacing_past_zero() {
+        let data = parse_input("down 2\nforward 5\nup 3\nforward 1");
+        assert_eq!(
+            follow_course(part1::Position::default(), &data),
+            Err(CourseError {
+                move_index: 2,
+                error: SurfacedError { depth: -1 }
+            })
+        );
+        let clamped = follow_course(
+            part1::Position::with_policy(DepthPolicy::ClampToSurface),
+            &data,
+        )
+        .unwrap();
+        assert_eq!((clamped.horizontal, clamped.depth), (6, 0));
+        let allowed =
+            follow_course(part1::Position::with_policy(DepthPolicy::Allow), &data).unwrap();
+        assert_eq!((allowed.horizontal, allowed.depth), (6, -1));
+        // The packed and wide sums agree with following the moves in order
+        let naive = |data: &[Move]| {
+            follow_course(part1::Position::default(), data).map(|position| position.pretty())
+        };
+        assert_eq!(part1_unordered(&data), naive(&data));
+        assert_eq!(part1_wide(&data), naive(&data));
+        let data = parse_input(
+            "forward 70000
+up 70000",
+        );
+        assert_eq!(part1_unordered(&data), naive(&data));
+        assert_eq!(
+            naive(&data),
+            Err(CourseError {
+                move_index: 1,
+                error: SurfacedError { depth: -70000 }
+            })
+        )
+    }
+    #[test]
+    fn test_part2_surfacing_past_zero() {
+        let data = parse_input("down 1\nforward 2\nup 3\nforward 2\nforward 1");
+        assert_eq!(
+            follow_course(part2::Position::default(), &data),
+            Err(CourseError {
+                move_index: 3,
+                error: SurfacedError { depth: -2 }
+            })
+        );
+        let clamped = follow_course(
+            part2::Position::with_policy(DepthPolicy::ClampToSurface),
+            &data,
+        )
+        .unwrap();
+        assert_eq!((clamped.aim, clamped.depth), (-2, 0));
+        let allowed =
+            follow_course(part2::Position::with_policy(DepthPolicy::Allow), &data).unwrap();
+        assert_eq!((allowed.aim, allowed.depth), (-2, -4))
+    }
 }
@@ -1,87 +1,449 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::num::ParseIntError;
 
-struct Input {
-    values: Vec<u16>,
+/// Unsigned integers that can hold one line of the diagnostic report
+pub trait Bits: Copy + Eq + Ord + std::fmt::Debug {
+    const BITS: usize;
+    fn from_binary(s: &str) -> Result<Self, ParseIntError>;
+    fn bit(self, index: usize) -> bool;
+    fn to_u128(self) -> u128;
+}
+
+macro_rules! impl_bits {
+    ($($t:ty),*) => {
+        $(
+            impl Bits for $t {
+                const BITS: usize = <$t>::BITS as usize;
+                fn from_binary(s: &str) -> Result<Self, ParseIntError> {
+                    <$t>::from_str_radix(s, 2)
+                }
+                fn bit(self, index: usize) -> bool {
+                    (self >> index) & 1 == 1
+                }
+                fn to_u128(self) -> u128 {
+                    self as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_bits!(u16, u32, u64, u128);
+
+pub struct Input<T> {
+    // Sorted once when parsing, so that ratings can narrow ranges of it (see `narrow_rating`)
+    values: Vec<T>,
     n_bits: usize,
 }
 
+/// The narrowest representation that fits the report's lines
+pub enum Report {
+    U16(Input<u16>),
+    U32(Input<u32>),
+    U64(Input<u64>),
+    U128(Input<u128>),
+}
+
+// Evaluate `$body` with `$input` bound to the report's `Input`, whatever its width
+macro_rules! with_input {
+    ($report:expr, $input:ident => $body:expr) => {
+        match $report {
+            Report::U16($input) => $body,
+            Report::U32($input) => $body,
+            Report::U64($input) => $body,
+            Report::U128($input) => $body,
+        }
+    };
+}
+
+fn parse_values<T: Bits>(data: &str, n_bits: usize) -> Input<T> {
+    let mut values = data
+        .lines()
+        .map(|s| {
+            assert_eq!(s.len(), n_bits, "All lines should have the same width");
+            T::from_binary(s).unwrap()
+        })
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    Input { values, n_bits }
+}
+
 #[aoc_generator(day03)]
-fn parse_input(data: &str) -> Input {
-    Input {
-        values: data
-            .lines()
-            .map(|s| u16::from_str_radix(s, 2).unwrap())
-            .collect::<Vec<_>>(),
-        n_bits: data.lines().next().unwrap().chars().count(),
+fn parse_input(data: &str) -> Report {
+    let n_bits = data.lines().next().unwrap().chars().count();
+    match n_bits {
+        n if n <= <u16 as Bits>::BITS => Report::U16(parse_values(data, n_bits)),
+        n if n <= <u32 as Bits>::BITS => Report::U32(parse_values(data, n_bits)),
+        n if n <= <u64 as Bits>::BITS => Report::U64(parse_values(data, n_bits)),
+        n if n <= <u128 as Bits>::BITS => Report::U128(parse_values(data, n_bits)),
+        _ => panic!("Diagnostics wider than 128 bits are not supported"),
     }
 }
 
-fn count_bits(values: &[u16]) -> [u16; 16] {
-    values.iter().fold([0; 16], |mut counts, i| {
+// Reference implementation for `count_bits_in_lanes`
+#[cfg(test)]
+fn count_bits<T: Bits>(values: &[T], n_bits: usize) -> Vec<usize> {
+    values.iter().fold(vec![0; n_bits], |mut counts, i| {
         for (bit_index, count) in counts.iter_mut().enumerate() {
-            *count += i >> bit_index & 1;
+            *count += i.bit(bit_index) as usize;
         }
         counts
     })
 }
 
-fn aggregate_bit_counts(bit_counts: &[u16], pred: impl Fn(u16) -> bool) -> usize {
-    bit_counts
-        .iter()
-        .enumerate()
-        .fold(0, |mut value, (index, bit_count)| {
-            if pred(*bit_count) {
-                value |= 1 << index;
+/*
+ * Counting one bit at a time costs a shift, a mask and an add per bit of every
+ * value. Instead, spread each 64 bit word of a value over 8 accumulators,
+ * where accumulator `shift` holds, in each of its 8 bytes, the counter of bit
+ * `8 * byte + shift`. Adding a word is then 8 word-wide adds of `word >> shift`
+ * masked to the low bit of every byte, whatever the number of bits.
+ * A byte can count up to 255 values before overflowing, so the report is
+ * processed in chunks of that size, after each of which the bytes are
+ * accumulated into the final counts.
+ */
+const BYTE_LANES: u64 = 0x0101_0101_0101_0101;
+
+fn count_bits_in_lanes<T: Bits>(values: &[T], n_bits: usize) -> Vec<usize> {
+    let n_words = n_bits.div_ceil(64);
+    let mut counts = vec![0; n_words * 64];
+    let mut lanes = vec![[0_u64; 8]; n_words];
+    for chunk in values.chunks(u8::MAX as usize) {
+        for value in chunk {
+            let value = value.to_u128();
+            for (word_index, accumulators) in lanes.iter_mut().enumerate() {
+                let word = (value >> (64 * word_index)) as u64;
+                for (shift, accumulator) in accumulators.iter_mut().enumerate() {
+                    *accumulator += (word >> shift) & BYTE_LANES;
+                }
             }
-            value
-        })
+        }
+        for (word_index, accumulators) in lanes.iter_mut().enumerate() {
+            for (shift, accumulator) in accumulators.iter_mut().enumerate() {
+                for byte in 0..8 {
+                    counts[64 * word_index + 8 * byte + shift] +=
+                        ((*accumulator >> (8 * byte)) & 0xff) as usize;
+                }
+                *accumulator = 0;
+            }
+        }
+    }
+    counts.truncate(n_bits);
+    counts
+}
+
+fn aggregate_bits(bits: &[bool]) -> u128 {
+    bits.iter()
+        .enumerate()
+        .fold(0, |value, (index, bit)| value | (*bit as u128) << index)
 }
 
-fn negate_n_bits(x: usize, n_bits: usize) -> usize {
-    let relevant_bits_mask = !(!0 << n_bits);
+fn negate_n_bits(x: u128, n_bits: usize) -> u128 {
+    let relevant_bits_mask = if n_bits >= 128 { !0 } else { !(!0 << n_bits) };
     (!x) & relevant_bits_mask
 }
 
-#[aoc(day03, part1)]
-fn part1(data: &Input) -> usize {
+/// The product of two rates or ratings, which takes up to 256 bits for a 128 bit report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct U256 {
+    high: u128,
+    low: u128,
+}
+
+impl U256 {
+    pub fn product(a: u128, b: u128) -> Self {
+        // Schoolbook multiplication of 64 bit halves, whose products all fit in a u128
+        let half_mask = u64::MAX as u128;
+        let (a_high, a_low) = (a >> 64, a & half_mask);
+        let (b_high, b_low) = (b >> 64, b & half_mask);
+        let low_low = a_low * b_low;
+        let low_high = a_low * b_high;
+        let high_low = a_high * b_low;
+        let middle = (low_low >> 64) + (low_high & half_mask) + (high_low & half_mask);
+        U256 {
+            high: a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64),
+            low: (low_low & half_mask) | (middle << 64),
+        }
+    }
+    // Divide in place by `divisor`, one 64 bit limb at a time from the top, and return the remainder
+    fn div_rem(&mut self, divisor: u64) -> u64 {
+        let mut remainder = 0_u128;
+        let mut limbs = [self.high >> 64, self.high, self.low >> 64, self.low].map(|limb| {
+            let dividend = remainder << 64 | (limb as u64) as u128;
+            remainder = dividend % divisor as u128;
+            dividend / divisor as u128
+        });
+        limbs.reverse();
+        self.low = limbs[0] | limbs[1] << 64;
+        self.high = limbs[2] | limbs[3] << 64;
+        remainder as u64
+    }
+}
+
+impl PartialEq<u128> for U256 {
+    fn eq(&self, other: &u128) -> bool {
+        self.high == 0 && self.low == *other
+    }
+}
+
+impl std::fmt::Display for U256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.high == 0 {
+            return write!(f, "{}", self.low);
+        }
+        // Groups of 19 decimal digits, the most that fit in a u64, from the lowest
+        const GROUP: u64 = 10_000_000_000_000_000_000;
+        let mut rest = *self;
+        let mut groups = Vec::new();
+        while rest.high != 0 || rest.low >= GROUP as u128 {
+            groups.push(rest.div_rem(GROUP));
+        }
+        write!(f, "{}", rest.low)?;
+        for group in groups.iter().rev() {
+            write!(f, "{:019}", group)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a column's most common bit is when it holds as many 1s as 0s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TiePolicy {
+    PreferOne,
+    PreferZero,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rating {
+    // Keeps the values with the most common bit
+    OxygenGenerator,
+    // Keeps the values with the least common bit
+    Co2Scrubber,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticError {
+    // A column is evenly split and the tie policy doesn't allow picking a bit
+    Tie { bit_index: usize },
+    // None of the candidates has the bit that should be kept
+    NoCandidateLeft { bit_index: usize },
+    // Every bit was considered but several (identical) candidates remain
+    NotUnique { n_candidates: usize },
+}
+
+impl std::fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Tie { bit_index } => write!(f, "bit {} has as many 1s as 0s", bit_index),
+            Self::NoCandidateLeft { bit_index } => {
+                write!(f, "no candidate left after filtering on bit {}", bit_index)
+            }
+            Self::NotUnique { n_candidates } => {
+                write!(
+                    f,
+                    "{} candidates left after filtering on every bit",
+                    n_candidates
+                )
+            }
+        }
+    }
+}
+
+fn most_common_bit(
+    bit_count: usize,
+    len: usize,
+    bit_index: usize,
+    policy: TiePolicy,
+) -> Result<bool, DiagnosticError> {
+    match (bit_count * 2).cmp(&len) {
+        std::cmp::Ordering::Greater => Ok(true),
+        std::cmp::Ordering::Less => Ok(false),
+        std::cmp::Ordering::Equal => match policy {
+            TiePolicy::PreferOne => Ok(true),
+            TiePolicy::PreferZero => Ok(false),
+            TiePolicy::Error => Err(DiagnosticError::Tie { bit_index }),
+        },
+    }
+}
+
+fn rates<T: Bits>(data: &Input<T>, policy: TiePolicy) -> Result<(u128, u128), DiagnosticError> {
     let len = data.values.len();
-    let bit_counts = count_bits(&data.values);
-    let gamma_rate = aggregate_bit_counts(&bit_counts, |bit_count| bit_count as usize > (len / 2));
+    let most_common_bits = count_bits_in_lanes(&data.values, data.n_bits)
+        .iter()
+        .enumerate()
+        .map(|(bit_index, bit_count)| most_common_bit(*bit_count, len, bit_index, policy))
+        .collect::<Result<Vec<_>, _>>()?;
+    let gamma_rate = aggregate_bits(&most_common_bits);
     let epsilon_rate = negate_n_bits(gamma_rate, data.n_bits);
-    epsilon_rate * gamma_rate
+    Ok((gamma_rate, epsilon_rate))
+}
+
+/// One filtering step of a rating's derivation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub bit_index: usize,
+    // Number of candidates with this bit set, before filtering
+    pub bit_count: usize,
+    pub kept_bit: bool,
+    pub candidates: Vec<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatingTrace {
+    pub steps: Vec<TraceStep>,
+    pub rating: Result<u128, DiagnosticError>,
 }
 
-fn get_rating(data: &Input, pred: impl Fn(u16, usize) -> bool) -> u16 {
+impl Report {
+    /// Gamma and epsilon rates
+    pub fn rates(&self, policy: TiePolicy) -> Result<(u128, u128), DiagnosticError> {
+        with_input!(self, input => rates(input, policy))
+    }
+    /// Oxygen generator and CO2 scrubber ratings
+    pub fn ratings(&self, policy: TiePolicy) -> Result<(u128, u128), DiagnosticError> {
+        with_input!(self, input => Ok((
+            narrow_rating(&input.values, input.n_bits, Rating::OxygenGenerator, policy)?.to_u128(),
+            narrow_rating(&input.values, input.n_bits, Rating::Co2Scrubber, policy)?.to_u128(),
+        )))
+    }
+    fn ratings_by_filtering(&self, policy: TiePolicy) -> Result<(u128, u128), DiagnosticError> {
+        with_input!(self, input => Ok((
+            get_rating(input, Rating::OxygenGenerator, policy)?.to_u128(),
+            get_rating(input, Rating::Co2Scrubber, policy)?.to_u128(),
+        )))
+    }
+    /// How `rating` is derived, one bit position at a time
+    pub fn trace_rating(&self, rating: Rating, policy: TiePolicy) -> RatingTrace {
+        let mut steps = Vec::new();
+        let rating = with_input!(self, input => filter_rating(
+            input,
+            rating,
+            policy,
+            |bit_index, bit_count, kept_bit, candidates| {
+                steps.push(TraceStep {
+                    bit_index,
+                    bit_count,
+                    kept_bit,
+                    candidates: candidates.iter().map(|value| value.to_u128()).collect(),
+                })
+            },
+        )
+        .map(|value| value.to_u128()));
+        RatingTrace { steps, rating }
+    }
+}
+
+#[aoc(day03, part1)]
+fn part1(data: &Report) -> U256 {
+    // Ties never occur in the puzzle's input; historically, they were counted as 0
+    let (gamma_rate, epsilon_rate) = data.rates(TiePolicy::PreferZero).unwrap();
+    U256::product(epsilon_rate, gamma_rate)
+}
+
+// Filter the candidates one bit at a time, from the top bit, and report each step to `on_step`
+fn filter_rating<T: Bits>(
+    data: &Input<T>,
+    rating: Rating,
+    policy: TiePolicy,
+    mut on_step: impl FnMut(usize, usize, bool, &[T]),
+) -> Result<T, DiagnosticError> {
     let mut kept = data.values.clone();
     for top_bit_index in (0..data.n_bits).rev() {
-        let bit_count = count_bits(&kept)[top_bit_index];
-        let should_keep_bit = pred(bit_count, kept.len());
-        kept = kept
-            .iter()
-            .partition(|value| {
-                let bit_set = ((**value >> top_bit_index) & 1) == 1;
-                bit_set == should_keep_bit
-            })
-            .0;
         if kept.len() == 1 {
-            return kept[0];
+            return Ok(kept[0]);
+        }
+        let bit_count = kept.iter().filter(|value| value.bit(top_bit_index)).count();
+        let most_common = most_common_bit(bit_count, kept.len(), top_bit_index, policy)?;
+        let should_keep_bit = match rating {
+            Rating::OxygenGenerator => most_common,
+            Rating::Co2Scrubber => !most_common,
+        };
+        kept.retain(|value| value.bit(top_bit_index) == should_keep_bit);
+        on_step(top_bit_index, bit_count, should_keep_bit, &kept);
+        if kept.is_empty() {
+            return Err(DiagnosticError::NoCandidateLeft {
+                bit_index: top_bit_index,
+            });
+        }
+    }
+    match kept.as_slice() {
+        [value] => Ok(*value),
+        _ => Err(DiagnosticError::NotUnique {
+            n_candidates: kept.len(),
+        }),
+    }
+}
+
+fn get_rating<T: Bits>(
+    data: &Input<T>,
+    rating: Rating,
+    policy: TiePolicy,
+) -> Result<T, DiagnosticError> {
+    filter_rating(data, rating, policy, |_, _, _, _| {})
+}
+
+/*
+ * Once the values are sorted, the candidates sharing the bits kept so far are
+ * always a contiguous range, where the ones with a 0 at the next bit come
+ * before the ones with a 1. Each step only has to binary search for the
+ * boundary between the two and narrow the range to one side of it, so a
+ * rating costs O(bits * log(n)) without copying or touching the values.
+ */
+fn narrow_rating<T: Bits>(
+    sorted: &[T],
+    n_bits: usize,
+    rating: Rating,
+    policy: TiePolicy,
+) -> Result<T, DiagnosticError> {
+    let mut kept = sorted;
+    for top_bit_index in (0..n_bits).rev() {
+        if kept.len() == 1 {
+            return Ok(kept[0]);
         }
+        let boundary = kept.partition_point(|value| !value.bit(top_bit_index));
+        let bit_count = kept.len() - boundary;
+        let most_common = most_common_bit(bit_count, kept.len(), top_bit_index, policy)?;
+        let should_keep_bit = match rating {
+            Rating::OxygenGenerator => most_common,
+            Rating::Co2Scrubber => !most_common,
+        };
+        kept = if should_keep_bit {
+            &kept[boundary..]
+        } else {
+            &kept[..boundary]
+        };
+        if kept.is_empty() {
+            return Err(DiagnosticError::NoCandidateLeft {
+                bit_index: top_bit_index,
+            });
+        }
+    }
+    match kept {
+        [value] => Ok(*value),
+        _ => Err(DiagnosticError::NotUnique {
+            n_candidates: kept.len(),
+        }),
     }
-    unreachable!("Prerequisite: we will find exactly one such number")
 }
 
 #[aoc(day03, part2)]
-fn part2(data: &Input) -> usize {
-    let oxygen_generator_rating =
-        get_rating(data, |bit_count, len| bit_count as usize * 2 >= len) as usize;
-    let co2_generator_rating =
-        get_rating(data, |bit_count, len| bit_count as usize * 2 < len) as usize;
-    oxygen_generator_rating * co2_generator_rating
+fn part2(data: &Report) -> U256 {
+    // As in the puzzle, ties keep 1s for the oxygen generator and 0s for the CO2 scrubber
+    let (oxygen_generator_rating, co2_generator_rating) =
+        data.ratings(TiePolicy::PreferOne).unwrap();
+    U256::product(oxygen_generator_rating, co2_generator_rating)
+}
+
+#[aoc(day03, part2, Filtering)]
+fn part2_filtering(data: &Report) -> U256 {
+    let (oxygen_generator_rating, co2_generator_rating) =
+        data.ratings_by_filtering(TiePolicy::PreferOne).unwrap();
+    U256::product(oxygen_generator_rating, co2_generator_rating)
 }
 
 #[cfg(test)]
 mod tests {
-    fn input() -> Input {
+    fn input() -> Report {
         parse_input(include_str!("../input/2021/day3.txt"))
     }
     const EXAMPLE_INPUT_STR: &'static str = "00100
@@ -96,10 +458,11 @@ mod tests {
 11001
 00010
 01010";
-    fn example_input() -> Input {
+    fn example_input() -> Report {
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::random::Random;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 198)
@@ -116,4 +479,232 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 5410338)
     }
+    #[test]
+    fn test_part2_filtering_given_example_input() {
+        assert_eq!(part2_filtering(&example_input()), 230)
+    }
+    #[test]
+    fn test_part2_filtering() {
+        assert_eq!(part2_filtering(&input()), 5410338)
+    }
+    fn random_bits(random: &mut Random, n_bits: usize) -> String {
+        (0..n_bits)
+            .map(|_| if random.below(2) == 0 { '0' } else { '1' })
+            .collect()
+    }
+    // Pseudo random report, along with its rates computed one column at a time
+    fn generate_report(seed: u64, n_lines: usize, n_bits: usize) -> (String, u128, u128) {
+        let mut random = Random::new(seed);
+        let lines = (0..n_lines)
+            .map(|_| random_bits(&mut random, n_bits))
+            .collect::<Vec<_>>();
+        let (mut gamma_rate, mut epsilon_rate) = (0, 0);
+        for column in 0..n_bits {
+            let ones = lines
+                .iter()
+                .filter(|line| line.as_bytes()[column] == b'1')
+                .count();
+            let most_common = ones > n_lines / 2;
+            gamma_rate = gamma_rate << 1 | most_common as u128;
+            epsilon_rate = epsilon_rate << 1 | !most_common as u128;
+        }
+        (lines.join("\n"), gamma_rate, epsilon_rate)
+    }
+    #[test]
+    fn test_rates_given_wide_reports() {
+        for (seed, n_bits) in [
+            (1, 5),
+            (2, 16),
+            (3, 17),
+            (4, 40),
+            (5, 64),
+            (6, 100),
+            (7, 128),
+        ] {
+            let (data, gamma_rate, epsilon_rate) = generate_report(seed, 301, n_bits);
+            assert_eq!(
+                parse_input(&data).rates(TiePolicy::PreferZero),
+                Ok((gamma_rate, epsilon_rate))
+            )
+        }
+    }
+    #[test]
+    fn test_ratings_given_wide_reports() {
+        // The example's ratings are found within its 5 bits, whatever comes after them
+        for (seed, n_bits) in [(1, 16), (2, 17), (3, 64), (4, 65), (5, 128)] {
+            let mut random = Random::new(seed);
+            let lines = EXAMPLE_INPUT_STR
+                .lines()
+                .map(|line| format!("{}{}", line, random_bits(&mut random, n_bits - 5)))
+                .collect::<Vec<_>>();
+            let rating = |prefix| {
+                let line = lines.iter().find(|line| line.starts_with(prefix)).unwrap();
+                u128::from_str_radix(line, 2).unwrap()
+            };
+            let report = parse_input(&lines.join("\n"));
+            assert_eq!(
+                report.ratings(TiePolicy::PreferOne),
+                Ok((rating("10111"), rating("01010")))
+            )
+        }
+    }
+    #[test]
+    fn test_rates_given_many_lines() {
+        // More lines than fit in a u16 count
+        let mut data = vec!["101"; 70_000];
+        data.extend(vec!["010"; 69_999]);
+        let report = parse_input(&data.join("\n"));
+        assert_eq!(report.rates(TiePolicy::Error), Ok((0b101, 0b010)));
+        assert_eq!(part1(&report), 10)
+    }
+    #[test]
+    fn test_parts_given_128_bit_report() {
+        let top_half = format!("{}{}", "1".repeat(64), "0".repeat(64));
+        // gamma = (2^64 - 1) << 64 and epsilon = 2^64 - 1
+        let data = [top_half.clone(), top_half.clone(), "0".repeat(128)].join("\n");
+        assert_eq!(
+            part1(&parse_input(&data)).to_string(),
+            "6277101735386680763155224689365789489194052973674207641600"
+        );
+        // oxygen generator = 2^128 - 1 and CO2 scrubber = 2^127 - 1
+        let data = ["1".repeat(128), top_half, format!("0{}", "1".repeat(127))].join("\n");
+        let expected =
+            "57896044618658097711785492504343953926124568782438874324533730092808912502785";
+        assert_eq!(part2(&parse_input(&data)).to_string(), expected);
+        assert_eq!(part2_filtering(&parse_input(&data)).to_string(), expected)
+    }
+    #[test]
+    fn test_u256_product() {
+        assert_eq!(U256::product(0, u128::MAX), 0);
+        assert_eq!(U256::product(1 << 64, 1 << 63), 1 << 127);
+        assert_eq!(
+            U256::product(u128::MAX, u128::MAX).to_string(),
+            "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+        );
+        // Exactly a power of ten, with groups of zeros to pad
+        let ten_to_19 = 10_u128.pow(19);
+        assert_eq!(
+            U256::product(ten_to_19 * ten_to_19, ten_to_19 * ten_to_19).to_string(),
+            format!("1{}", "0".repeat(76))
+        )
+    }
+    #[test]
+    fn test_trace_oxygen_generator_rating_given_example_input() {
+        let trace = example_input().trace_rating(Rating::OxygenGenerator, TiePolicy::PreferOne);
+        let steps = trace
+            .steps
+            .iter()
+            .map(|step| (step.bit_index, step.bit_count, step.kept_bit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                (4, 7, true),
+                (3, 3, false),
+                (2, 3, true),
+                (1, 2, true),
+                (0, 1, true)
+            ]
+        );
+        assert_eq!(
+            trace.steps[1].candidates,
+            vec![0b10000, 0b10101, 0b10110, 0b10111]
+        );
+        assert_eq!(trace.steps[4].candidates, vec![0b10111]);
+        assert_eq!(trace.rating, Ok(23))
+    }
+    #[test]
+    fn test_ratings_given_tie_policy() {
+        // The last step of the oxygen generator rating is a tie between 10110 and 10111
+        let report = example_input();
+        assert_eq!(
+            report.ratings(TiePolicy::Error),
+            Err(DiagnosticError::Tie { bit_index: 0 })
+        );
+        let trace = report.trace_rating(Rating::OxygenGenerator, TiePolicy::PreferZero);
+        assert_eq!(trace.rating, Ok(22));
+        assert_eq!(report.rates(TiePolicy::Error), Ok((0b10110, 0b01001)))
+    }
+    #[test]
+    fn test_ratings_errors() {
+        let duplicates = parse_input("101\n101\n000");
+        assert_eq!(
+            duplicates.ratings(TiePolicy::PreferOne),
+            Err(DiagnosticError::NotUnique { n_candidates: 2 })
+        );
+        let emptied = parse_input("000\n001\n011");
+        assert_eq!(
+            emptied
+                .trace_rating(Rating::Co2Scrubber, TiePolicy::PreferOne)
+                .rating,
+            Err(DiagnosticError::NoCandidateLeft { bit_index: 2 })
+        );
+        let single = parse_input("10110");
+        assert_eq!(single.ratings(TiePolicy::Error), Ok((22, 22)))
+    }
+    #[test]
+    fn test_ratings_match_filtering_given_generated_reports() {
+        for (seed, n_lines, n_bits) in [(1, 10, 4), (2, 1000, 12), (3, 1000, 40), (4, 5000, 70)] {
+            let (data, _, _) = generate_report(seed, n_lines, n_bits);
+            let report = parse_input(&data);
+            for policy in [
+                TiePolicy::PreferOne,
+                TiePolicy::PreferZero,
+                TiePolicy::Error,
+            ] {
+                assert_eq!(report.ratings(policy), report.ratings_by_filtering(policy))
+            }
+        }
+        for data in ["101\n101\n000", "000\n001\n011", "10110"] {
+            let report = parse_input(data);
+            assert_eq!(
+                report.ratings(TiePolicy::PreferOne),
+                report.ratings_by_filtering(TiePolicy::PreferOne)
+            )
+        }
+    }
+    #[test]
+    #[ignore]
+    fn bench_ratings_given_generated_report() {
+        let (data, _, _) = generate_report(9, 1_000_000, 32);
+        let report = parse_input(&data);
+        crate::bench::compare(
+            "1000000 lines of 32 bits",
+            ("filtering", || {
+                report.ratings_by_filtering(TiePolicy::PreferOne)
+            }),
+            ("narrowing", || report.ratings(TiePolicy::PreferOne)),
+        )
+    }
+    #[test]
+    fn test_count_bits_in_lanes_matches_count_bits() {
+        for (seed, n_lines, n_bits) in
+            [(1, 1, 5), (2, 1000, 16), (3, 70_000, 33), (4, 140_000, 128)]
+        {
+            let (data, _, _) = generate_report(seed, n_lines, n_bits);
+            with_input!(&parse_input(&data), input => assert_eq!(
+                count_bits_in_lanes(&input.values, input.n_bits),
+                count_bits(&input.values, input.n_bits)
+            ))
+        }
+        // Counters that fill every byte, on both sides of a chunk boundary
+        for n_lines in [255, 256, 3 * 255 + 1] {
+            let values = vec![u16::MAX; n_lines];
+            assert_eq!(count_bits_in_lanes(&values, 16), vec![n_lines; 16]);
+            assert_eq!(count_bits_in_lanes(&values, 16), count_bits(&values, 16))
+        }
+    }
+    #[test]
+    #[ignore]
+    fn bench_count_bits_given_large_report() {
+        let mut random = Random::new(9);
+        let values = (0..20_000_000)
+            .map(|_| random.next_u64() as u32)
+            .collect::<Vec<_>>();
+        crate::bench::compare(
+            &format!("{} lines of 32 bits", values.len()),
+            ("scalar", || count_bits(&values, 32)),
+            ("in lanes", || count_bits_in_lanes(&values, 32)),
+        )
+    }
 }
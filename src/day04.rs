@@ -1,98 +1,393 @@
+use crate::random::Random;
+use crate::simulation::Simulation;
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::iter::repeat;
 use std::str::FromStr;
 
-const BINGO_GRID_COLS: usize = 5;
-const BINGO_GRID_ROWS: usize = 5;
+// Numbers as parsed, before they are stored in the narrowest `Value` that fits the input
+type Number = u32;
+
+/// Unsigned integers that can hold every number of a game
+pub trait Value: Copy + Eq + Ord + Hash + std::fmt::Debug {
+    const MAX: Number;
+    // Only called with numbers up to `MAX`
+    fn narrow(number: Number) -> Self;
+    fn widen(self) -> Number;
+}
+
+macro_rules! impl_value {
+    ($($t:ty),*) => {
+        $(
+            impl Value for $t {
+                const MAX: Number = <$t>::MAX as Number;
+                fn narrow(number: Number) -> Self {
+                    number as $t
+                }
+                fn widen(self) -> Number {
+                    self as Number
+                }
+            }
+        )*
+    };
+}
+
+impl_value!(u8, u16, u32);
 
 #[derive(Debug, Clone)]
-struct Board {
-    data: [[u8; BINGO_GRID_COLS]; BINGO_GRID_ROWS],
+struct Board<N> {
+    n_rows: usize,
+    n_cols: usize,
+    // Row major
+    data: Vec<N>,
+}
+
+impl<N: Value> Board<N> {
+    // The same board with its numbers stored as `T`, which must hold all of them
+    fn with_width<T: Value>(&self) -> Board<T> {
+        Board {
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+            data: self.data.iter().map(|val| T::narrow(val.widen())).collect(),
+        }
+    }
 }
 
-impl FromStr for Board {
+impl FromStr for Board<Number> {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut data = [[0; BINGO_GRID_ROWS]; BINGO_GRID_COLS];
-        s.lines()
-            .filter(|line| !line.is_empty())
-            .enumerate()
-            .try_for_each(|(row_index, line)| {
+        let rows = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
                 line.split_whitespace()
-                    .enumerate()
-                    .try_for_each(|(col_index, word)| {
-                        data[row_index][col_index] = word
-                            .parse::<u8>()
-                            .map_err(|_| "Failed to parse board value")?;
-                        Ok(())
+                    .map(|word| {
+                        word.parse::<Number>().map_err(|_| {
+                            "Failed to parse board value: numbers should be between 0 and 4294967295"
+                        })
                     })
-            })?;
-        Ok(Self { data })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let n_cols = rows.first().ok_or("Empty board")?.len();
+        if rows.iter().any(|row| row.len() != n_cols) {
+            return Err("Ragged board: all rows should have the same length");
+        }
+        Ok(Self {
+            n_rows: rows.len(),
+            n_cols,
+            data: rows.concat(),
+        })
     }
 }
 
-#[derive(Debug)]
-struct VisitedBoard {
-    row_first: u32,
-    col_first: u32,
+/// A set of cells of a board, stored as one bit per cell in row major order
+trait Marks: Clone + std::fmt::Debug {
+    // Number of cells that fit in the set
+    const CAPACITY: usize;
+    fn empty(n_cells: usize) -> Self;
+    fn mark(&mut self, cell: usize);
+    fn is_marked(&self, cell: usize) -> bool;
+    // Whether every cell of `mask` is marked
+    fn covers(&self, mask: &Self) -> bool;
 }
 
-impl VisitedBoard {
-    fn new() -> Self {
-        Self {
-            row_first: 0,
-            col_first: 0,
-        }
+macro_rules! impl_marks {
+    ($($t:ty),*) => {
+        $(
+            impl Marks for $t {
+                const CAPACITY: usize = <$t>::BITS as usize;
+                fn empty(_n_cells: usize) -> Self {
+                    0
+                }
+                fn mark(&mut self, cell: usize) {
+                    *self |= 1 << cell;
+                }
+                fn is_marked(&self, cell: usize) -> bool {
+                    (self >> cell) & 1 == 1
+                }
+                fn covers(&self, mask: &Self) -> bool {
+                    self & mask == *mask
+                }
+            }
+        )*
+    };
+}
+
+impl_marks!(u32, u64, u128);
+
+// Boards too large for a single integer
+impl Marks for Vec<u64> {
+    const CAPACITY: usize = usize::MAX;
+    fn empty(n_cells: usize) -> Self {
+        vec![0; n_cells.div_ceil(64)]
     }
-    fn row_first_index(row: usize, col: usize) -> usize {
-        row * BINGO_GRID_COLS + col
+    fn mark(&mut self, cell: usize) {
+        self[cell / 64] |= 1 << (cell % 64);
     }
-    fn col_first_index(row: usize, col: usize) -> usize {
-        col * BINGO_GRID_ROWS + row
+    fn is_marked(&self, cell: usize) -> bool {
+        (self[cell / 64] >> (cell % 64)) & 1 == 1
     }
-    fn visit(&mut self, row: usize, col: usize) {
-        self.row_first |= 1 << Self::row_first_index(row, col);
-        self.col_first |= 1 << Self::col_first_index(row, col);
+    fn covers(&self, mask: &Self) -> bool {
+        self.iter()
+            .zip(mask)
+            .all(|(marks, mask)| marks & mask == *mask)
     }
-    fn winning(&self) -> bool {
-        for index in 0..BINGO_GRID_ROWS {
-            if (self.row_first >> (5 * index)).trailing_ones() as usize >= BINGO_GRID_COLS {
-                return true;
+}
+
+// Evaluate `$body` with `$marks` aliased to the narrowest `Marks` that fits the input's boards
+macro_rules! with_marks {
+    ($input:expr, $marks:ident => $body:expr) => {
+        match $input.n_cells() {
+            n if n <= <u32 as Marks>::CAPACITY => {
+                type $marks = u32;
+                $body
             }
-        }
-        for index in 0..BINGO_GRID_COLS {
-            if (self.col_first >> (5 * index)).trailing_ones() as usize >= BINGO_GRID_ROWS {
-                return true;
+            n if n <= <u64 as Marks>::CAPACITY => {
+                type $marks = u64;
+                $body
+            }
+            n if n <= <u128 as Marks>::CAPACITY => {
+                type $marks = u128;
+                $body
+            }
+            _ => {
+                type $marks = Vec<u64>;
+                $body
             }
         }
-        false
+    };
+}
+
+fn mask_of<M: Marks>(n_cells: usize, cells: impl Iterator<Item = usize>) -> M {
+    cells.fold(M::empty(n_cells), |mut mask, cell| {
+        mask.mark(cell);
+        mask
+    })
+}
+
+/// A custom winning shape, drawn with '#' for the cells to mark and '.' for the others.
+/// It must have the same size as the boards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    n_rows: usize,
+    n_cols: usize,
+    // Row major
+    cells: Vec<bool>,
+}
+
+impl FromStr for Shape {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err("Shapes should only contain '#' and '.'"),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let n_cols = rows.first().ok_or("Empty shape")?.len();
+        if rows.iter().any(|row| row.len() != n_cols) {
+            return Err("Ragged shape: all rows should have the same length");
+        }
+        let cells = rows.concat();
+        if !cells.iter().any(|cell| *cell) {
+            return Err("Shapes should have at least one cell to mark");
+        }
+        Ok(Self {
+            n_rows: rows.len(),
+            n_cols,
+            cells,
+        })
     }
-    fn row_first(&self) -> [[bool; BINGO_GRID_COLS]; BINGO_GRID_ROWS] {
-        let mut ret = [[false; BINGO_GRID_COLS]; BINGO_GRID_ROWS];
-        for (row_index, row) in ret.iter_mut().enumerate() {
-            for (col_index, val) in row.iter_mut().enumerate() {
-                *val = (self.row_first >> Self::row_first_index(row_index, col_index)) & 1 == 1;
+}
+
+/// A family of cell sets, any of which wins when all of its cells are marked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WinPattern {
+    Rows,
+    Columns,
+    // Both diagonals of square boards
+    Diagonals,
+    // The four corners at once
+    Corners,
+    // Every cell of the board
+    Blackout,
+    Shape(Shape),
+}
+
+impl FromStr for WinPattern {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "rows" => Ok(Self::Rows),
+            "columns" => Ok(Self::Columns),
+            "diagonals" => Ok(Self::Diagonals),
+            "corners" => Ok(Self::Corners),
+            "blackout" => Ok(Self::Blackout),
+            shape => Ok(Self::Shape(Shape::from_str(shape)?)),
+        }
+    }
+}
+
+impl WinPattern {
+    fn check(&self, n_rows: usize, n_cols: usize) -> Result<(), &'static str> {
+        match self {
+            Self::Diagonals if n_rows != n_cols => Err("Diagonals need square boards"),
+            Self::Shape(shape) if (shape.n_rows, shape.n_cols) != (n_rows, n_cols) => {
+                Err("Shapes should have the same size as the boards")
             }
+            _ => Ok(()),
+        }
+    }
+    fn masks<M: Marks>(&self, n_rows: usize, n_cols: usize) -> Vec<M> {
+        let n_cells = n_rows * n_cols;
+        let cell = |row: usize, col: usize| row * n_cols + col;
+        match self {
+            Self::Rows => (0..n_rows)
+                .map(|row| mask_of(n_cells, (0..n_cols).map(|col| cell(row, col))))
+                .collect(),
+            Self::Columns => (0..n_cols)
+                .map(|col| mask_of(n_cells, (0..n_rows).map(|row| cell(row, col))))
+                .collect(),
+            Self::Diagonals => vec![
+                mask_of(n_cells, (0..n_rows).map(|i| cell(i, i))),
+                mask_of(n_cells, (0..n_rows).map(|i| cell(i, n_cols - 1 - i))),
+            ],
+            Self::Corners => vec![mask_of(
+                n_cells,
+                [
+                    cell(0, 0),
+                    cell(0, n_cols - 1),
+                    cell(n_rows - 1, 0),
+                    cell(n_rows - 1, n_cols - 1),
+                ]
+                .into_iter(),
+            )],
+            Self::Blackout => vec![mask_of(n_cells, 0..n_cells)],
+            Self::Shape(shape) => vec![mask_of(
+                n_cells,
+                (0..n_cells).filter(|index| shape.cells[*index]),
+            )],
         }
-        ret
+    }
+}
+
+#[derive(Debug)]
+struct VisitedBoard<M> {
+    marks: M,
+}
+
+impl<M: Marks> VisitedBoard<M> {
+    fn new(n_cells: usize) -> Self {
+        Self {
+            marks: M::empty(n_cells),
+        }
+    }
+    fn visit(&mut self, cell: usize) {
+        self.marks.mark(cell)
+    }
+    fn winning(&self, win_masks: &[M]) -> bool {
+        win_masks.iter().any(|mask| self.marks.covers(mask))
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 struct Position {
     board_index: usize,
-    row_index: usize,
-    col_index: usize,
+    cell_index: usize,
+}
+
+/*
+ * A vector indexed by number is the fastest way to find where a number is on
+ * the boards, but it must span every number up to the largest one. When that
+ * span is much larger than the number of cells, a hash map is used instead.
+ */
+const MIN_DENSE_SPAN: usize = 1 << 12;
+const MAX_SPAN_PER_CELL: usize = 4;
+
+#[derive(Debug, Clone)]
+enum PositionIndex<N> {
+    Dense(Vec<Vec<Position>>),
+    Sparse(HashMap<N, Vec<Position>>),
+}
+
+impl<N: Value> PositionIndex<N> {
+    fn new(boards: &[Board<N>]) -> Self {
+        let positions = boards.iter().enumerate().flat_map(|(board_index, board)| {
+            board.data.iter().enumerate().map(move |(cell_index, val)| {
+                (
+                    *val,
+                    Position {
+                        board_index,
+                        cell_index,
+                    },
+                )
+            })
+        });
+        let n_cells = boards.iter().map(|board| board.data.len()).sum::<usize>();
+        let span = boards
+            .iter()
+            .flat_map(|board| board.data.iter())
+            .max()
+            .map_or(0, |max| max.widen() as usize + 1);
+        if span <= MIN_DENSE_SPAN.max(MAX_SPAN_PER_CELL * n_cells) {
+            let mut index = vec![Vec::new(); span];
+            positions.for_each(|(val, position)| index[val.widen() as usize].push(position));
+            Self::Dense(index)
+        } else {
+            let mut index = HashMap::<N, Vec<Position>>::new();
+            positions.for_each(|(val, position)| index.entry(val).or_default().push(position));
+            Self::Sparse(index)
+        }
+    }
+    // Where `number` is on the boards, if anywhere
+    fn get(&self, number: N) -> &[Position] {
+        match self {
+            Self::Dense(index) => index.get(number.widen() as usize),
+            Self::Sparse(index) => index.get(&number),
+        }
+        .map_or(&[], |positions| positions.as_slice())
+    }
 }
 
-const MAX_BINGO_VALUE: usize = 100;
+#[derive(Debug, Clone)]
+pub struct Input<N> {
+    nums: Vec<N>,
+    // All boards have the same size
+    n_rows: usize,
+    n_cols: usize,
+    boards: Vec<Board<N>>,
+    positions: PositionIndex<N>,
+    patterns: Vec<WinPattern>,
+}
 
+/// The narrowest representation that fits the largest number of the game
 #[derive(Debug, Clone)]
-struct BingoInput {
-    nums: Vec<u8>,
-    boards: Vec<Board>,
-    positions: Vec<Vec<Position>>,
+pub enum BingoInput {
+    U8(Input<u8>),
+    U16(Input<u16>),
+    U32(Input<u32>),
+}
+
+// Evaluate `$body` with `$input` bound to the game's `Input`, whatever its width
+macro_rules! with_input {
+    ($bingo_input:expr, $input:ident => $body:expr) => {
+        match $bingo_input {
+            BingoInput::U8($input) => $body,
+            BingoInput::U16($input) => $body,
+            BingoInput::U32($input) => $body,
+        }
+    };
 }
 
 impl FromStr for BingoInput {
@@ -103,45 +398,169 @@ impl FromStr for BingoInput {
             .split("\n\n")
             .map(Board::from_str)
             .collect::<Result<Vec<_>, _>>()?;
-        let mut positions: Vec<Vec<Position>> = repeat(Vec::with_capacity(100))
-            .take(MAX_BINGO_VALUE)
-            .collect();
-        boards.iter().enumerate().for_each(|(board_index, board)| {
-            board.data.iter().enumerate().for_each(|(row_index, row)| {
-                row.iter().enumerate().for_each(|(col_index, val)| {
-                    positions[*val as usize].push(Position {
-                        board_index,
-                        row_index,
-                        col_index,
-                    });
+        let nums = nums_str
+            .split(',')
+            .map(|s| {
+                s.parse().map_err(|_| {
+                    "Incorrect value in nums: numbers should be between 0 and 4294967295"
                 })
             })
-        });
+            .collect::<Result<_, _>>()?;
+        Self::new(nums, boards)
+    }
+}
+
+impl BingoInput {
+    fn new(nums: Vec<Number>, boards: Vec<Board<Number>>) -> Result<Self, &'static str> {
+        let max = nums
+            .iter()
+            .chain(boards.iter().flat_map(|board| board.data.iter()))
+            .max()
+            .map_or(0, |max| *max);
+        Ok(match max {
+            n if n <= <u8 as Value>::MAX => Self::U8(Input::new(&nums, &boards)?),
+            n if n <= <u16 as Value>::MAX => Self::U16(Input::new(&nums, &boards)?),
+            _ => Self::U32(Input::new(&nums, &boards)?),
+        })
+    }
+    fn boards(&self) -> Vec<Board<Number>> {
+        with_input!(self, input => input.boards.iter().map(Board::with_width).collect())
+    }
+    // The same boards and patterns with other draws, which may need a wider representation
+    fn with_nums(&self, nums: Vec<Number>) -> Result<Self, &'static str> {
+        let patterns = with_input!(self, input => input.patterns.clone());
+        Self::new(nums, self.boards())?.with_patterns(patterns)
+    }
+    /// Replace the winning patterns, which are full rows and columns by default
+    pub fn with_patterns(self, patterns: Vec<WinPattern>) -> Result<Self, &'static str> {
+        Ok(match self {
+            Self::U8(input) => Self::U8(input.with_patterns(patterns)?),
+            Self::U16(input) => Self::U16(input.with_patterns(patterns)?),
+            Self::U32(input) => Self::U32(input.with_patterns(patterns)?),
+        })
+    }
+}
 
+impl<N: Value> Input<N> {
+    fn new(nums: &[Number], boards: &[Board<Number>]) -> Result<Self, &'static str> {
+        let (n_rows, n_cols) = (boards[0].n_rows, boards[0].n_cols);
+        if boards
+            .iter()
+            .any(|board| (board.n_rows, board.n_cols) != (n_rows, n_cols))
+        {
+            return Err("All boards should have the same size");
+        }
+        let boards = boards.iter().map(Board::with_width).collect::<Vec<_>>();
+        let positions = PositionIndex::new(&boards);
         Ok(Self {
-            nums: nums_str
-                .split(',')
-                .map(|s| s.parse().map_err(|_| "Incorrect value in nums"))
-                .collect::<Result<_, _>>()?,
+            nums: nums.iter().map(|num| N::narrow(*num)).collect(),
+            n_rows,
+            n_cols,
             boards,
             positions,
+            patterns: vec![WinPattern::Rows, WinPattern::Columns],
         })
     }
+    fn n_cells(&self) -> usize {
+        self.n_rows * self.n_cols
+    }
+    fn win_masks<M: Marks>(&self) -> Vec<M> {
+        self.patterns
+            .iter()
+            .flat_map(|pattern| pattern.masks(self.n_rows, self.n_cols))
+            .collect()
+    }
+    fn with_patterns(mut self, patterns: Vec<WinPattern>) -> Result<Self, &'static str> {
+        if patterns.is_empty() {
+            return Err("At least one winning pattern is needed");
+        }
+        for pattern in &patterns {
+            pattern.check(self.n_rows, self.n_cols)?;
+        }
+        self.patterns = patterns;
+        Ok(self)
+    }
 }
 
 #[derive(Debug)]
-struct Bingo {
-    input: BingoInput,
-    visited: Vec<VisitedBoard>,
+struct Bingo<M, N> {
+    input: Input<N>,
+    visited: Vec<VisitedBoard<M>>,
+    // Cell sets that win when fully marked
+    win_masks: Vec<M>,
+    // None to rescan every board after every draw instead
+    counters: Option<MaskCounters>,
     index_to_draw_next: usize,
     won: Vec<bool>,
 }
 
-impl Bingo {
-    fn from_input(input: &BingoInput) -> Self {
+/*
+ * Rather than checking every win mask of every board after each draw, count
+ * how many cells of each mask are still unmarked on each board. A draw only
+ * updates the counters of the masks containing the cells it marks, found
+ * through `positions`, and a board wins as soon as one of them reaches 0.
+ */
+#[derive(Debug)]
+struct MaskCounters {
+    // For each cell, the indices of the win masks containing it
+    masks_by_cell: Vec<Vec<usize>>,
+    // For each board, then each win mask, the number of its cells left to mark
+    unmarked: Vec<u32>,
+    n_masks: usize,
+}
+
+impl MaskCounters {
+    fn new<M: Marks>(win_masks: &[M], n_cells: usize, n_boards: usize) -> Self {
+        let masks_by_cell = (0..n_cells)
+            .map(|cell| {
+                (0..win_masks.len())
+                    .filter(|mask_index| win_masks[*mask_index].is_marked(cell))
+                    .collect()
+            })
+            .collect();
+        let mask_sizes = win_masks
+            .iter()
+            .map(|mask| (0..n_cells).filter(|cell| mask.is_marked(*cell)).count() as u32)
+            .collect::<Vec<_>>();
+        Self {
+            masks_by_cell,
+            unmarked: mask_sizes.repeat(n_boards),
+            n_masks: win_masks.len(),
+        }
+    }
+    // Count a newly marked cell, returning whether it completed a win mask
+    fn mark(&mut self, board_index: usize, cell_index: usize) -> bool {
+        let unmarked = &mut self.unmarked[board_index * self.n_masks..][..self.n_masks];
+        let mut completed = false;
+        for mask_index in &self.masks_by_cell[cell_index] {
+            unmarked[*mask_index] -= 1;
+            completed |= unmarked[*mask_index] == 0;
+        }
+        completed
+    }
+}
+
+impl<M: Marks, N: Value> Bingo<M, N> {
+    fn from_input(input: &Input<N>) -> Self {
+        let mut bingo = Self::scanning(input);
+        bingo.counters = Some(MaskCounters::new(
+            &bingo.win_masks,
+            input.n_cells(),
+            input.boards.len(),
+        ));
+        bingo
+    }
+    // Find winners by checking every board that didn't win yet after every draw
+    fn scanning(input: &Input<N>) -> Self {
         Self {
             input: input.clone(),
-            visited: input.boards.iter().map(|_| VisitedBoard::new()).collect(),
+            visited: input
+                .boards
+                .iter()
+                .map(|_| VisitedBoard::new(input.n_cells()))
+                .collect(),
+            win_masks: input.win_masks(),
+            counters: None,
             index_to_draw_next: 0,
             won: repeat(false).take(input.boards.len()).collect(),
         }
@@ -155,7 +574,7 @@ impl Bingo {
                 !self.won[*board_index]
             })
             .filter_map(|(board_index, board)| {
-                let winning = board.winning();
+                let winning = board.winning(&self.win_masks);
                 if winning {
                     Some(board_index)
                 } else {
@@ -164,69 +583,389 @@ impl Bingo {
             })
             .collect()
     }
-    fn score(&self, winning_boards: Vec<usize>) -> usize {
+    fn unmarked_sum(&self, board_index: usize) -> u64 {
+        let marks = &self.visited[board_index].marks;
+        self.input.boards[board_index]
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(cell_index, _)| !marks.is_marked(*cell_index))
+            .map(|(_, val)| val.widen() as u64)
+            .sum::<u64>()
+    }
+    // Wide enough for any number drawn times the sum of many boards of large numbers
+    fn score(&self, winning_boards: Vec<usize>, draw: Number) -> u128 {
         winning_boards
             .iter()
-            .map(|winning_board| {
-                self.visited[*winning_board]
-                    .row_first()
-                    .iter()
-                    .enumerate()
-                    .map(|(row_index, row)| {
-                        row.iter()
-                            .enumerate()
-                            .map(|(col_index, col)| {
-                                if *col {
-                                    0
-                                } else {
-                                    self.input.boards[*winning_board].data[row_index][col_index]
-                                        as usize
-                                }
-                            })
-                            .sum::<usize>()
-                    })
-                    .sum::<usize>()
-            })
-            .sum::<usize>()
+            .map(|winning_board| self.unmarked_sum(*winning_board) as u128)
+            .sum::<u128>()
+            * draw as u128
     }
 }
 
-impl Iterator for Bingo {
-    type Item = (usize, Vec<usize>);
+impl<M: Marks, N: Value> Iterator for Bingo<M, N> {
+    type Item = (Number, Vec<usize>);
     fn next(&mut self) -> Option<Self::Item> {
         if self.index_to_draw_next >= self.input.nums.len() {
             return None;
         }
         let draw = self.input.nums[self.index_to_draw_next];
-        for pos in &self.input.positions[draw as usize] {
-            self.visited[pos.board_index].visit(pos.row_index, pos.col_index)
+        let mut completed = Vec::new();
+        for pos in self.input.positions.get(draw) {
+            let visited = &mut self.visited[pos.board_index];
+            // A number drawn twice mustn't be counted twice
+            if visited.marks.is_marked(pos.cell_index) {
+                continue;
+            }
+            visited.visit(pos.cell_index);
+            if let Some(counters) = &mut self.counters {
+                if counters.mark(pos.board_index, pos.cell_index) && !self.won[pos.board_index] {
+                    completed.push(pos.board_index);
+                }
+            }
         }
         self.index_to_draw_next += 1;
-        let winning_boards = self.winning_boards();
+        let winning_boards = if self.counters.is_some() {
+            completed.sort_unstable();
+            completed.dedup();
+            completed
+        } else {
+            self.winning_boards()
+        };
         for winning_board in &winning_boards {
             self.won[*winning_board] = true;
         }
-        Some((draw as usize, winning_boards))
+        Some((draw.widen(), winning_boards))
     }
 }
 
+impl<M: Marks, N: Value> Simulation for Bingo<M, N> {}
+
 #[aoc_generator(day04)]
 fn parse_input(data: &str) -> BingoInput {
     BingoInput::from_str(data).unwrap()
 }
 
-#[aoc(day04, part1)]
-fn part1(data: &BingoInput) -> usize {
-    let mut bingo = Bingo::from_input(data);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Win {
+    // Index of the winning draw among all draws
+    pub draw_index: usize,
+    pub number: Number,
+}
+
+/// How a board fared over a whole game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardResult {
+    pub board_index: usize,
+    // None if the board never won
+    pub win: Option<Win>,
+    // When the board won, or at the end of the game if it never did
+    pub unmarked_sum: u64,
+    pub score: Option<u128>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameReport {
+    pub boards: Vec<BoardResult>,
+}
+
+impl GameReport {
+    /// Winners first, in the order they won, then the boards that never won
+    pub fn sort_by_finishing_order(&mut self) {
+        self.boards.sort_by_key(|board| {
+            (
+                board.win.map_or(usize::MAX, |win| win.draw_index),
+                board.board_index,
+            )
+        })
+    }
+}
+
+impl std::fmt::Display for GameReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>6} {:>6} {:>6} {:>8} {:>8}",
+            "board", "draw", "number", "unmarked", "score"
+        )?;
+        for board in &self.boards {
+            let (draw_index, number, score) = match (board.win, board.score) {
+                (Some(win), Some(score)) => (
+                    win.draw_index.to_string(),
+                    win.number.to_string(),
+                    score.to_string(),
+                ),
+                _ => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+            write!(
+                f,
+                "\n{:>6} {:>6} {:>6} {:>8} {:>8}",
+                board.board_index, draw_index, number, board.unmarked_sum, score
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn report<M: Marks, N: Value>(data: &Input<N>) -> GameReport {
+    let mut bingo = Bingo::<M, N>::from_input(data);
+    let mut boards = (0..data.boards.len())
+        .map(|board_index| BoardResult {
+            board_index,
+            win: None,
+            unmarked_sum: 0,
+            score: None,
+        })
+        .collect::<Vec<_>>();
+    while !bingo.won.iter().all(|won| *won) {
+        let (draw, winning_boards) = match bingo.next() {
+            Some(step) => step,
+            None => break,
+        };
+        for board_index in winning_boards {
+            let unmarked_sum = bingo.unmarked_sum(board_index);
+            boards[board_index] = BoardResult {
+                board_index,
+                win: Some(Win {
+                    draw_index: bingo.index_to_draw_next - 1,
+                    number: draw,
+                }),
+                unmarked_sum,
+                score: Some(unmarked_sum as u128 * draw as u128),
+            };
+        }
+    }
+    for board in boards.iter_mut().filter(|board| board.win.is_none()) {
+        board.unmarked_sum = bingo.unmarked_sum(board.board_index);
+    }
+    GameReport { boards }
+}
+
+impl BingoInput {
+    /// Play the whole game and report how every board fared, in the boards' order
+    pub fn report(&self) -> GameReport {
+        with_input!(self, input => input.report())
+    }
+}
+
+impl<N: Value> Input<N> {
+    fn report(&self) -> GameReport {
+        with_marks!(self, M => report::<M, N>(self))
+    }
+}
+
+// The first `n` numbers of a random permutation of `numbers`
+fn pick<N: Copy>(random: &mut Random, n: usize, numbers: &[N]) -> Vec<N> {
+    let mut numbers = numbers.to_vec();
+    for i in 0..n {
+        let j = i + random.below((numbers.len() - i) as u64) as usize;
+        numbers.swap(i, j);
+    }
+    numbers.truncate(n);
+    numbers
+}
+
+/// The shape of randomly generated games
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeckConfig {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub n_boards: usize,
+    // Boards and draws use the numbers 0..n_numbers
+    pub n_numbers: usize,
+}
+
+impl DeckConfig {
+    fn check(&self) -> Result<(), &'static str> {
+        if self.n_rows == 0 || self.n_cols == 0 || self.n_boards == 0 {
+            return Err("A deck needs at least one board of at least one cell");
+        }
+        if self.n_numbers < self.n_rows * self.n_cols {
+            return Err("Not enough numbers to fill a board without repetition");
+        }
+        check_n_numbers(self.n_numbers)
+    }
+    /// A random game: boards of distinct numbers, and all the numbers drawn in a random order
+    pub fn generate(&self, seed: u64) -> Result<BingoInput, &'static str> {
+        self.check()?;
+        let mut random = Random::new(seed);
+        let n_cells = self.n_rows * self.n_cols;
+        let numbers = (0..self.n_numbers).map(|x| x as Number).collect::<Vec<_>>();
+        let boards = (0..self.n_boards)
+            .map(|_| Board {
+                n_rows: self.n_rows,
+                n_cols: self.n_cols,
+                data: pick(&mut random, n_cells, &numbers),
+            })
+            .collect();
+        BingoInput::new(pick(&mut random, self.n_numbers, &numbers), boards)
+    }
+}
+
+fn check_n_numbers(n_numbers: usize) -> Result<(), &'static str> {
+    if n_numbers > Number::MAX as usize + 1 {
+        Err("Numbers should be between 0 and 4294967295")
+    } else {
+        Ok(())
+    }
+}
+
+/// Per board estimates, from simulating many games
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimates {
+    pub n_games: usize,
+    // Boards winning together all count as winning first (or last)
+    pub win_first: Vec<f64>,
+    pub win_last: Vec<f64>,
+    // Including the winning draw
+    pub expected_draws_to_first_win: f64,
+}
+
+impl BingoInput {
+    /// Play the boards against `n_games` random orders of draws of the numbers 0..n_numbers
+    pub fn monte_carlo(
+        &self,
+        n_numbers: usize,
+        n_games: usize,
+        seed: u64,
+    ) -> Result<Estimates, &'static str> {
+        check_n_numbers(n_numbers)?;
+        if n_games == 0 {
+            return Err("At least one game is needed");
+        }
+        // Drawing all the numbers once picks a width that fits them all
+        let game = self.with_nums((0..n_numbers).map(|x| x as Number).collect())?;
+        Ok(with_input!(&game, input => input.monte_carlo(n_games, seed)))
+    }
+}
+
+impl<N: Value> Input<N> {
+    // Every game draws the numbers of `self.nums` in a different order
+    fn monte_carlo(&self, n_games: usize, seed: u64) -> Estimates {
+        let mut random = Random::new(seed);
+        let mut game = self.clone();
+        let n_numbers = self.nums.len();
+        let n_boards = self.boards.len();
+        let (mut win_first, mut win_last) = (vec![0; n_boards], vec![0; n_boards]);
+        let mut total_draws_to_first_win = 0;
+        for _ in 0..n_games {
+            game.nums = pick(&mut random, n_numbers, &self.nums);
+            let report = game.report();
+            let draw_indices = report
+                .boards
+                .iter()
+                .map(|board| board.win.map(|win| win.draw_index))
+                .collect::<Vec<_>>();
+            let first = draw_indices.iter().flatten().min();
+            let last = draw_indices.iter().flatten().max();
+            for (board_index, draw_index) in draw_indices.iter().enumerate() {
+                if draw_index.is_some() && draw_index.as_ref() == first {
+                    win_first[board_index] += 1;
+                }
+                if draw_index.is_some() && draw_index.as_ref() == last {
+                    win_last[board_index] += 1;
+                }
+            }
+            // A game without any winner draws every number
+            total_draws_to_first_win += first.map_or(n_numbers, |first| first + 1);
+        }
+        let frequency = |count: usize| count as f64 / n_games as f64;
+        Estimates {
+            n_games,
+            win_first: win_first.into_iter().map(frequency).collect(),
+            win_last: win_last.into_iter().map(frequency).collect(),
+            expected_draws_to_first_win: frequency(total_draws_to_first_win),
+        }
+    }
+}
+
+/*
+ * To win, a board must have all the cells of one of its win masks marked, so
+ * the shortest way for it to win is to draw exactly the numbers of one of its
+ * masks. Drawing more numbers can only mark more cells of the other boards.
+ * The order of the draws doesn't matter either: once all of a mask's numbers
+ * are drawn, any other board they complete has won too, at the latest on the
+ * same draw. So a board can win first if and only if one of its masks' sets of
+ * numbers completes no other board, and the shortest such set is the answer.
+ */
+fn win_first_candidates<M: Marks, N: Value>(data: &Input<N>, board_index: usize) -> Vec<Vec<N>> {
+    let board = &data.boards[board_index];
+    let mut candidates = data
+        .win_masks::<M>()
+        .iter()
+        .map(|mask| {
+            let mut numbers = (0..data.n_cells())
+                .filter(|cell| mask.is_marked(*cell))
+                .map(|cell| board.data[cell])
+                .collect::<Vec<_>>();
+            numbers.sort_unstable();
+            numbers.dedup();
+            numbers
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|numbers| numbers.len());
+    candidates
+}
+
+fn shortest_win_first<M: Marks, N: Value>(data: &Input<N>, board_index: usize) -> Option<Vec<N>> {
+    let win_masks = data.win_masks::<M>();
+    win_first_candidates::<M, N>(data, board_index)
+        .into_iter()
+        .find(|numbers| {
+            let mut visited = data
+                .boards
+                .iter()
+                .map(|_| VisitedBoard::<M>::new(data.n_cells()))
+                .collect::<Vec<_>>();
+            for number in numbers {
+                for pos in data.positions.get(*number) {
+                    visited[pos.board_index].visit(pos.cell_index)
+                }
+            }
+            visited
+                .iter()
+                .enumerate()
+                .all(|(index, board)| index == board_index || !board.winning(&win_masks))
+        })
+}
+
+impl BingoInput {
+    fn check_board_index(&self, board_index: usize) -> Result<(), &'static str> {
+        if board_index < with_input!(self, input => input.boards.len()) {
+            Ok(())
+        } else {
+            Err("No board at this index")
+        }
+    }
+    /// The fewest draws making the board at `board_index` win strictly before every other
+    /// board, in any order. None if any way for it to win also makes another board win.
+    pub fn shortest_win_first(
+        &self,
+        board_index: usize,
+    ) -> Result<Option<Vec<Number>>, &'static str> {
+        self.check_board_index(board_index)?;
+        Ok(with_input!(self, input => with_marks!(input, M => {
+            shortest_win_first::<M, _>(input, board_index)
+                .map(|numbers| numbers.into_iter().map(Value::widen).collect())
+        })))
+    }
+    /// The fewest draws after which the board at `board_index` can win, regardless of the others
+    pub fn min_draws_to_win(&self, board_index: usize) -> Result<usize, &'static str> {
+        self.check_board_index(board_index)?;
+        Ok(with_input!(self, input => with_marks!(input, M => {
+            win_first_candidates::<M, _>(input, board_index)[0].len()
+        })))
+    }
+}
+
+fn first_winner_score<M: Marks, N: Value>(mut bingo: Bingo<M, N>) -> u128 {
     let (draw, winning) = bingo
         .find(|(_, winning_boards)| !winning_boards.is_empty())
         .unwrap();
-    bingo.score(winning) * draw
+    bingo.score(winning, draw)
 }
 
-#[aoc(day04, part2)]
-fn part2(data: &BingoInput) -> usize {
-    let mut bingo = Bingo::from_input(data);
+fn last_winner_score<M: Marks, N: Value>(mut bingo: Bingo<M, N>) -> u128 {
     let (mut draw, mut winning) = bingo
         .find(|(_, winning_boards)| !winning_boards.is_empty())
         .unwrap();
@@ -235,7 +974,22 @@ fn part2(data: &BingoInput) -> usize {
             .find(|(_, winning_boards)| !winning_boards.is_empty())
             .unwrap();
     }
-    bingo.score(winning) * draw
+    bingo.score(winning, draw)
+}
+
+#[aoc(day04, part1)]
+fn part1(data: &BingoInput) -> u128 {
+    with_input!(data, input => with_marks!(input, M => first_winner_score(Bingo::<M, _>::from_input(input))))
+}
+
+#[aoc(day04, part2)]
+fn part2(data: &BingoInput) -> u128 {
+    with_input!(data, input => with_marks!(input, M => last_winner_score(Bingo::<M, _>::from_input(input))))
+}
+
+#[aoc(day04, part2, Scan)]
+fn part2_scan(data: &BingoInput) -> u128 {
+    with_input!(data, input => with_marks!(input, M => last_winner_score(Bingo::<M, _>::scanning(input))))
 }
 
 #[cfg(test)]
@@ -267,6 +1021,10 @@ mod tests {
         parse_input(E04AMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::snapshot::assert_snapshot;
+    fn nums(data: &BingoInput) -> Vec<Number> {
+        with_input!(data, input => input.nums.iter().map(|num| num.widen()).collect())
+    }
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 4512)
@@ -283,4 +1041,407 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 4880)
     }
+    #[test]
+    fn test_part2_scan_given_example_input() {
+        assert_eq!(part2_scan(&example_input()), 1924)
+    }
+    #[test]
+    fn test_part2_scan() {
+        assert_eq!(part2_scan(&input()), 4880)
+    }
+    #[test]
+    fn test_observe_given_example_input() {
+        let mut won = Vec::new();
+        with_input!(&example_input(), input => Bingo::<u32, _>::from_input(input)
+            .observe(|bingo| won.push(bingo.won.clone()))
+            .take(12)
+            .for_each(drop));
+        assert!(won[..11].iter().all(|step| step.iter().all(|x| !x)));
+        assert_eq!(won[11], vec![false, false, true])
+    }
+    #[test]
+    fn test_scores_match_given_any_marks() {
+        with_input!(&example_input(), data => {
+            assert_eq!(first_winner_score(Bingo::<u64, _>::from_input(data)), 4512);
+            assert_eq!(
+                first_winner_score(Bingo::<Vec<u64>, _>::from_input(data)),
+                4512
+            );
+            assert_eq!(last_winner_score(Bingo::<u128, _>::from_input(data)), 1924);
+            assert_eq!(last_winner_score(Bingo::<Vec<u64>, _>::scanning(data)), 1924)
+        })
+    }
+    // A board numbered from 0 in row major order
+    fn numbered_board(n_rows: usize, n_cols: usize) -> String {
+        (0..n_rows)
+            .map(|row| {
+                (0..n_cols)
+                    .map(|col| (row * n_cols + col).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    #[test]
+    fn test_part1_given_larger_boards() {
+        // Drawing the numbers of the last column, from the bottom: the column wins
+        for (n_rows, n_cols) in [(3, 4), (6, 7), (10, 10), (9, 11)] {
+            let nums = (0..n_rows)
+                .rev()
+                .map(|row| (row * n_cols + n_cols - 1).to_string())
+                .collect::<Vec<_>>();
+            let data = parse_input(&format!(
+                "{}\n\n{}",
+                nums.join(","),
+                numbered_board(n_rows, n_cols)
+            ));
+            let unmarked_sum = (0..n_rows * n_cols).sum::<usize>()
+                - (0..n_rows)
+                    .map(|row| row * n_cols + n_cols - 1)
+                    .sum::<usize>();
+            assert_eq!(part1(&data), (unmarked_sum * (n_cols - 1)) as u128)
+        }
+    }
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Board::from_str("1 2 3\n4 5\n6 7 8").unwrap_err(),
+            "Ragged board: all rows should have the same length"
+        );
+        assert_eq!(
+            BingoInput::from_str("1,2\n\n1 2\n3 4\n\n1 2 3\n4 5 6").unwrap_err(),
+            "All boards should have the same size"
+        );
+        let board = Board::from_str(&numbered_board(2, 3)).unwrap();
+        assert_eq!((board.n_rows, board.n_cols), (2, 3));
+        assert_eq!(board.data[board.n_cols + 2], 5)
+    }
+    fn first_winners(data: &BingoInput, patterns: &str) -> (Number, Vec<usize>) {
+        let patterns = patterns
+            .split(';')
+            .map(WinPattern::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let data = data.clone().with_patterns(patterns).unwrap();
+        with_input!(&data, input => Bingo::<u32, _>::from_input(input)
+            .find(|(_, winning_boards)| !winning_boards.is_empty())
+            .unwrap())
+    }
+    #[test]
+    fn test_winners_given_patterns() {
+        let data = example_input();
+        assert_eq!(first_winners(&data, "rows;columns"), (24, vec![2]));
+        // The third board's anti-diagonal 4 9 23 11 2 is complete after drawing 2
+        assert_eq!(first_winners(&data, "diagonals"), (2, vec![2]));
+        // The third board's corners 14 4 2 7 are complete after drawing 14
+        assert_eq!(first_winners(&data, "corners"), (14, vec![2]));
+        // Only the second board has neither 1 nor 26, the last two draws
+        assert_eq!(first_winners(&data, "blackout"), (3, vec![1]));
+        // The second board's middle row 19 8 7 25 23 and middle column 0 13 7 10 16
+        let plus = "..#..\n..#..\n#####\n..#..\n..#..";
+        assert_eq!(first_winners(&data, plus), (19, vec![1]));
+        assert_eq!(
+            first_winners(&data, &format!("diagonals;{}", plus)),
+            (2, vec![2])
+        )
+    }
+    #[test]
+    fn test_pattern_errors() {
+        assert_eq!(
+            WinPattern::from_str("#.\n#").unwrap_err(),
+            "Ragged shape: all rows should have the same length"
+        );
+        assert_eq!(
+            WinPattern::from_str("#x").unwrap_err(),
+            "Shapes should only contain '#' and '.'"
+        );
+        let data = parse_input(&format!("1\n\n{}", numbered_board(2, 3)));
+        assert_eq!(
+            data.clone()
+                .with_patterns(vec![WinPattern::Diagonals])
+                .unwrap_err(),
+            "Diagonals need square boards"
+        );
+        assert_eq!(
+            data.with_patterns(vec![WinPattern::from_str("#.\n.#").unwrap()])
+                .unwrap_err(),
+            "Shapes should have the same size as the boards"
+        )
+    }
+    #[test]
+    fn test_report_given_example_input() {
+        let mut report = example_input().report();
+        let scores = report
+            .boards
+            .iter()
+            .map(|board| board.score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores[2], Some(part1(&example_input())));
+        report.sort_by_finishing_order();
+        assert_eq!(
+            report.boards.last().unwrap().score,
+            Some(part2(&example_input()))
+        );
+        assert_snapshot("day04_report_example", &report.to_string())
+    }
+    #[test]
+    fn test_report_given_boards_that_never_win() {
+        let data = parse_input(&format!(
+            "0,1,2\n\n{}\n\n{}",
+            numbered_board(2, 2),
+            "5 6\n7 8"
+        ));
+        let mut report = data.report();
+        report.sort_by_finishing_order();
+        assert_eq!(
+            report.boards,
+            vec![
+                BoardResult {
+                    board_index: 0,
+                    win: Some(Win {
+                        draw_index: 1,
+                        number: 1
+                    }),
+                    unmarked_sum: 5,
+                    score: Some(5)
+                },
+                BoardResult {
+                    board_index: 1,
+                    win: None,
+                    unmarked_sum: 26,
+                    score: None
+                }
+            ]
+        );
+        assert_snapshot("day04_report_never_won", &report.to_string())
+    }
+    #[test]
+    fn test_generate_is_deterministic() {
+        let config = DeckConfig {
+            n_rows: 5,
+            n_cols: 5,
+            n_boards: 20,
+            n_numbers: 75,
+        };
+        let (a, b) = (config.generate(7).unwrap(), config.generate(7).unwrap());
+        assert_eq!(nums(&a), nums(&b));
+        assert_eq!(part1(&a), part1(&b));
+        assert_ne!(nums(&a), nums(&config.generate(8).unwrap()));
+        let mut nums = nums(&a);
+        nums.sort_unstable();
+        assert_eq!(nums, (0..75).collect::<Vec<Number>>());
+        for board in &a.boards() {
+            let mut data = board.data.clone();
+            data.sort_unstable();
+            data.dedup();
+            assert_eq!(data.len(), 25);
+            assert!(data.iter().all(|x| *x < 75))
+        }
+        assert_eq!(
+            DeckConfig {
+                n_numbers: 20,
+                ..config
+            }
+            .generate(7)
+            .unwrap_err(),
+            "Not enough numbers to fill a board without repetition"
+        )
+    }
+    #[test]
+    fn test_monte_carlo_given_single_cell() {
+        // The only cell's number is equally likely to be drawn at any position
+        let data = parse_input("0\n\n3");
+        let estimates = data.monte_carlo(10, 20_000, 1).unwrap();
+        assert_eq!(estimates.win_first, vec![1.0]);
+        assert!((estimates.expected_draws_to_first_win - 5.5).abs() < 0.1);
+        assert_eq!(estimates, data.monte_carlo(10, 20_000, 1).unwrap())
+    }
+    #[test]
+    fn test_monte_carlo_given_generated_deck() {
+        let config = DeckConfig {
+            n_rows: 3,
+            n_cols: 3,
+            n_boards: 4,
+            n_numbers: 30,
+        };
+        let deck = config.generate(3).unwrap();
+        // The same board twice always wins at the same time as itself
+        let mut boards = deck.boards();
+        boards[1] = boards[0].clone();
+        let deck = BingoInput::new(nums(&deck), boards).unwrap();
+        let estimates = deck.monte_carlo(30, 2000, 5).unwrap();
+        assert_eq!(estimates.win_first[0], estimates.win_first[1]);
+        assert_eq!(estimates.win_last[0], estimates.win_last[1]);
+        assert!(estimates.win_first.iter().sum::<f64>() >= 1.0);
+        assert!(estimates.win_last.iter().sum::<f64>() >= 1.0);
+        assert!(estimates.expected_draws_to_first_win >= 3.0);
+        assert!(estimates.expected_draws_to_first_win <= 30.0)
+    }
+    #[test]
+    fn test_part1_given_large_numbers() {
+        // The example, with every number shifted past u8 and then far apart
+        for (scale, offset, dense) in [(1, 200, true), (10_007, 1_000, false)] {
+            let shift = |word: &str| (word.parse::<u64>().unwrap() * scale + offset).to_string();
+            let (nums, boards) = E04AMPLE_INPUT_STR.split_once("\n\n").unwrap();
+            let nums = nums.split(',').map(shift).collect::<Vec<_>>().join(",");
+            let boards = boards
+                .lines()
+                .map(|line| {
+                    line.split_whitespace()
+                        .map(shift)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let data = parse_input(&format!("{}\n\n{}", nums, boards));
+            assert_eq!(
+                with_input!(&data, input => matches!(input.positions, PositionIndex::Dense(_))),
+                dense
+            );
+            let unmarked_sum = 188 * scale as u128 + 13 * offset as u128;
+            let draw = 24 * scale as u128 + offset as u128;
+            assert_eq!(part1(&data), unmarked_sum * draw)
+        }
+    }
+    #[test]
+    fn test_scores_given_numbers_near_u32_max() {
+        // The first row wins, and its score doesn't fit in a u64
+        let base: Number = 4_000_000_000;
+        let nums = (0..5).map(|i| (base + i).to_string()).collect::<Vec<_>>();
+        let boards = (0..5)
+            .map(|row| {
+                (0..5)
+                    .map(|col| (base + row * 5 + col).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        let data = parse_input(&format!("{}\n\n{}", nums.join(","), boards.join("\n")));
+        let unmarked_sum = (5..25).map(|i| (base + i) as u128).sum::<u128>();
+        let score = unmarked_sum * (base + 4) as u128;
+        assert!(score > u64::MAX as u128);
+        assert_eq!(part1(&data), score);
+        assert_eq!(part2(&data), score);
+        assert_eq!(part2_scan(&data), score);
+        assert_eq!(data.report().boards[0].score, Some(score))
+    }
+    #[test]
+    fn test_draws_missing_from_boards() {
+        let data = parse_input("4000000000,7,1,2\n\n1 2\n3 4");
+        assert!(
+            matches!(&data, BingoInput::U32(input) if matches!(input.positions, PositionIndex::Dense(_)))
+        );
+        assert_eq!(part1(&data), 7 * 2)
+    }
+    #[test]
+    fn test_width_given_largest_number() {
+        assert!(matches!(example_input(), BingoInput::U8(_)));
+        assert!(matches!(parse_input("1,255\n\n1 2"), BingoInput::U8(_)));
+        // Draws count too, even when they're on no board
+        assert!(matches!(parse_input("1,256\n\n1 2"), BingoInput::U16(_)));
+        assert!(matches!(parse_input("1\n\n1 65536"), BingoInput::U32(_)));
+        // Random draws past the boards' width widen the game
+        let estimates = parse_input("0\n\n3").monte_carlo(300, 10, 1).unwrap();
+        assert_eq!(estimates.win_first, vec![1.0])
+    }
+    #[test]
+    fn test_number_parse_errors() {
+        assert_eq!(
+            BingoInput::from_str("1,2\n\n1 5000000000").unwrap_err(),
+            "Failed to parse board value: numbers should be between 0 and 4294967295"
+        );
+        assert_eq!(
+            BingoInput::from_str("1,-2\n\n1 2").unwrap_err(),
+            "Incorrect value in nums: numbers should be between 0 and 4294967295"
+        )
+    }
+    #[test]
+    fn test_counters_match_scanning() {
+        let config = DeckConfig {
+            n_rows: 5,
+            n_cols: 5,
+            n_boards: 200,
+            n_numbers: 60,
+        };
+        let patterns = [
+            "rows;columns",
+            "diagonals;corners",
+            "blackout",
+            "rows;rows;#...#\n.#.#.\n..#..\n.#.#.\n#...#",
+        ];
+        for (seed, patterns) in patterns.iter().enumerate() {
+            let data = config
+                .generate(seed as u64)
+                .unwrap()
+                .with_patterns(
+                    patterns
+                        .split(';')
+                        .map(|pattern| WinPattern::from_str(pattern).unwrap())
+                        .collect(),
+                )
+                .unwrap();
+            // Numbers drawn twice
+            let mut nums = nums(&data);
+            nums.extend_from_within(..30);
+            nums.rotate_right(30);
+            let data = data.with_nums(nums).unwrap();
+            with_input!(&data, input => {
+                let counting = Bingo::<u32, _>::from_input(input).collect::<Vec<_>>();
+                let scanning = Bingo::<u32, _>::scanning(input).collect::<Vec<_>>();
+                assert_eq!(counting, scanning)
+            })
+        }
+    }
+    #[test]
+    #[ignore]
+    fn bench_part2_given_large_deck() {
+        let data = DeckConfig {
+            n_rows: 5,
+            n_cols: 5,
+            n_boards: 100_000,
+            n_numbers: 100,
+        }
+        .generate(9)
+        .unwrap();
+        crate::bench::compare(
+            &format!("{} boards", data.boards().len()),
+            ("scanning", || part2_scan(&data)),
+            ("counting", || part2(&data)),
+        )
+    }
+    #[test]
+    fn test_shortest_win_first_given_example_input() {
+        let data = example_input();
+        for board_index in 0..data.boards().len() {
+            let draws = data.shortest_win_first(board_index).unwrap().unwrap();
+            assert_eq!(draws.len(), 5);
+            assert_eq!(data.min_draws_to_win(board_index), Ok(5));
+            let mut report = data.with_nums(draws).unwrap().report();
+            report.sort_by_finishing_order();
+            assert_eq!(report.boards[0].board_index, board_index);
+            assert_eq!(report.boards[0].win.unwrap().draw_index, 4);
+            assert!(report.boards[1..].iter().all(|board| board.win.is_none()))
+        }
+    }
+    #[test]
+    fn test_shortest_win_first() {
+        // The first row would make both boards win
+        let data = parse_input("1\n\n1 2\n3 4\n\n1 2\n5 6");
+        assert_eq!(data.shortest_win_first(0), Ok(Some(vec![3, 4])));
+        assert_eq!(data.shortest_win_first(1), Ok(Some(vec![5, 6])));
+        // Every line of each board is a line of the other one
+        let data = parse_input("1\n\n1 2\n3 4\n\n1 3\n2 4");
+        assert_eq!(data.shortest_win_first(0), Ok(None));
+        assert_eq!(data.min_draws_to_win(0), Ok(2));
+        // The same number twice on a line only needs one draw
+        let data = parse_input("1\n\n1 1 2\n3 4 5\n6 7 8");
+        assert_eq!(data.min_draws_to_win(0), Ok(2));
+        assert_eq!(data.shortest_win_first(0), Ok(Some(vec![1, 2])));
+        let data = data.with_patterns(vec![WinPattern::Blackout]).unwrap();
+        assert_eq!(data.min_draws_to_win(0), Ok(8));
+        assert_eq!(data.min_draws_to_win(1), Err("No board at this index"));
+        assert_eq!(data.shortest_win_first(1), Err("No board at this index"))
+    }
 }
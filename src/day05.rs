@@ -1,12 +1,13 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use std::collections::HashMap;
 use std::iter::repeat;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
-    row: u16,
-    col: u16,
+    row: i64,
+    col: i64,
 }
 
 impl FromStr for Point {
@@ -41,7 +42,7 @@ impl FromStr for Segment {
     }
 }
 
-fn move_start_towards_end(start: &mut u16, end: u16) {
+fn move_start_towards_end(start: &mut i64, end: i64) {
     match (*start).cmp(&end) {
         std::cmp::Ordering::Less => {
             *start += 1;
@@ -96,8 +97,8 @@ impl Iterator for OrthogonalOrDiagonalSegmentIterator {
                 move_start_towards_end(&mut self.0.start.row, self.0.end.row);
                 Some(prev)
                 // diagonal
-            } else if (self.0.end.row as i16 - self.0.start.row as i16).abs()
-                == (self.0.end.col as i16 - self.0.start.col as i16).abs()
+            } else if self.0.end.row.abs_diff(self.0.start.row)
+                == self.0.end.col.abs_diff(self.0.start.col)
             {
                 move_start_towards_end(&mut self.0.start.row, self.0.end.row);
                 move_start_towards_end(&mut self.0.start.col, self.0.end.col);
@@ -114,60 +115,448 @@ enum MappingMode {
     OrthogonalOrDiagonal,
 }
 
+// Call `f` with every point of the segment that counts under `mapping_mode`
+fn for_each_point(segment: Segment, mapping_mode: &MappingMode, mut f: impl FnMut(Point)) {
+    let mut last_segment = None;
+    match mapping_mode {
+        MappingMode::Orthogonal => {
+            for shorter_segment in OrthogonalSegmentIterator(segment) {
+                last_segment = Some(shorter_segment);
+                f(shorter_segment.start);
+            }
+        }
+        MappingMode::OrthogonalOrDiagonal => {
+            for shorter_segment in OrthogonalOrDiagonalSegmentIterator(segment) {
+                last_segment = Some(shorter_segment);
+                f(shorter_segment.start);
+            }
+        }
+    };
+    if let Some(last_segment) = last_segment {
+        f(last_segment.end);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Storage {
+    // One counter per point of the bounding box
+    Dense,
+    // Counters for the points that are covered only
+    Sparse,
+}
+
+/*
+ * The dense grid is the fastest, but it allocates the whole bounding box of the
+ * segments. That's fine as long as the box isn't much larger than the number of
+ * points the segments cover (or is small anyway). Otherwise, e.g. with a few long
+ * segments millions of cells apart, counting in a hash map is cheaper.
+ */
+const MAX_SMALL_AREA: u128 = 1 << 20;
+const MAX_AREA_PER_POINT: u128 = 16;
+const MAX_DENSE_AREA: u128 = 1 << 30;
+
+struct BoundingBox {
+    min: Point,
+    n_rows: u128,
+    n_cols: u128,
+}
+
+impl BoundingBox {
+    fn of(segments: &[Segment]) -> Self {
+        let points = || {
+            segments
+                .iter()
+                .flat_map(|segment| [segment.start, segment.end])
+        };
+        let min_row = points().map(|p| p.row).min().unwrap();
+        let max_row = points().map(|p| p.row).max().unwrap();
+        let min_col = points().map(|p| p.col).min().unwrap();
+        let max_col = points().map(|p| p.col).max().unwrap();
+        Self {
+            min: Point {
+                row: min_row,
+                col: min_col,
+            },
+            n_rows: (max_row as i128 - min_row as i128 + 1) as u128,
+            n_cols: (max_col as i128 - min_col as i128 + 1) as u128,
+        }
+    }
+    fn area(&self) -> u128 {
+        self.n_rows.saturating_mul(self.n_cols)
+    }
+    fn row_major_index(&self, p: Point) -> usize {
+        (p.row - self.min.row) as usize * self.n_cols as usize + (p.col - self.min.col) as usize
+    }
+}
+
+impl Storage {
+    fn for_segments(segments: &[Segment]) -> Self {
+        let area = BoundingBox::of(segments).area();
+        // Upper bound on the number of points covered by the segments
+        let n_points = segments
+            .iter()
+            .map(|segment| {
+                let n_rows = (segment.end.row as i128 - segment.start.row as i128).unsigned_abs();
+                let n_cols = (segment.end.col as i128 - segment.start.col as i128).unsigned_abs();
+                n_rows.max(n_cols) + 1
+            })
+            .sum::<u128>();
+        if area <= MAX_SMALL_AREA
+            || (area <= MAX_DENSE_AREA && area <= MAX_AREA_PER_POINT * n_points)
+        {
+            Self::Dense
+        } else {
+            Self::Sparse
+        }
+    }
+}
+
 #[derive(Debug)]
-struct Grid {
-    // Optimization: allocate some space, but get very fast access in return
+enum Grid {
+    // Optimization: allocate a counter per cell of the bounding box, but get very fast access in return
     // Benchmarked against HashMap and BTreeMap
     // This is 4-5 times faster than BTreeMap and 2-3 times faster than HashMap
-    data: Vec<usize>,
-}
-
-fn row_major_index(p: Point, n_cols: usize) -> usize {
-    p.row as usize * n_cols + p.col as usize
+    Dense(Vec<usize>),
+    // Only the visited points, for segments so far apart that the bounding box would be
+    // mostly empty, or too large to allocate (see `Storage::for_segments`)
+    Sparse(HashMap<Point, usize>),
 }
 
 impl Grid {
     fn from_segments(segments: &[Segment], mapping_mode: MappingMode) -> Self {
-        let n_cols = segments
-            .iter()
-            .map(|segment| segment.start.col.max(segment.end.col))
-            .max()
-            .unwrap() as usize
-            + 1;
-        let n_rows = segments
-            .iter()
-            .map(|segment| segment.start.row.max(segment.end.row))
-            .max()
-            .unwrap() as usize
-            + 1;
-        let mut data: Vec<usize> = repeat(0).take(n_cols * n_rows).collect();
+        Self::with_storage(segments, mapping_mode, Storage::for_segments(segments))
+    }
+    fn with_storage(segments: &[Segment], mapping_mode: MappingMode, storage: Storage) -> Self {
+        match storage {
+            Storage::Dense => {
+                let bounding_box = BoundingBox::of(segments);
+                let mut data: Vec<usize> = repeat(0).take(bounding_box.area() as usize).collect();
+                for segment in segments {
+                    for_each_point(*segment, &mapping_mode, |p| {
+                        data[bounding_box.row_major_index(p)] += 1
+                    });
+                }
+                Self::Dense(data)
+            }
+            Storage::Sparse => {
+                let mut data = HashMap::new();
+                for segment in segments {
+                    for_each_point(*segment, &mapping_mode, |p| {
+                        *data.entry(p).or_insert(0) += 1
+                    });
+                }
+                Self::Sparse(data)
+            }
+        }
+    }
+    fn count_gt_one(&self) -> usize {
+        let count = |values: &mut dyn Iterator<Item = &usize>| {
+            values.map(|value| if *value > 1 { 1 } else { 0 }).sum()
+        };
+        match self {
+            Self::Dense(data) => count(&mut data.iter()),
+            Self::Sparse(data) => count(&mut data.values()),
+        }
+    }
+}
+
+/*
+ * Counts the points covered by at least two segments without visiting the
+ * points of the segments one by one.
+ *
+ * Every counted segment is on a line of one of 4 directions, and each line is
+ * identified by a key (e.g. the row of a horizontal line). Within a line, an
+ * interval sweep gives the ranges covered at least once and at least twice.
+ * A point covered by at least two segments is then either covered twice within
+ * a single direction, or a crossing of lines of two directions.
+ * Crossings are found by a sweep over the keys of one direction, keeping the
+ * lines of the other direction that are active at that key in an ordered map,
+ * so the work depends on the number of segments and crossings only.
+ */
+mod sweep {
+    use super::{MappingMode, Segment};
+    use std::collections::BTreeMap;
+
+    // Keys and lengths of lines between i64 coordinates can take one more bit
+    type Coord = i128;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Point {
+        row: Coord,
+        col: Coord,
+    }
+
+    impl From<super::Point> for Point {
+        fn from(p: super::Point) -> Self {
+            Point {
+                row: p.row as Coord,
+                col: p.col as Coord,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Direction {
+        Horizontal,
+        Vertical,
+        // Row and column increase together
+        Diagonal,
+        // Row increases as column decreases
+        AntiDiagonal,
+    }
+
+    impl Direction {
+        fn of(segment: &Segment) -> Option<Self> {
+            let (start, end) = (Point::from(segment.start), Point::from(segment.end));
+            let rows = end.row - start.row;
+            let cols = end.col - start.col;
+            match (rows, cols) {
+                // The rasterising grid never counts single points either
+                (0, 0) => None,
+                (0, _) => Some(Self::Horizontal),
+                (_, 0) => Some(Self::Vertical),
+                _ if rows == cols => Some(Self::Diagonal),
+                _ if rows == -cols => Some(Self::AntiDiagonal),
+                _ => None,
+            }
+        }
+        // The line's key is `row * a + col * b`
+        fn coefficients(self) -> (Coord, Coord) {
+            match self {
+                Self::Horizontal => (1, 0),
+                Self::Vertical => (0, 1),
+                Self::Diagonal => (1, -1),
+                Self::AntiDiagonal => (1, 1),
+            }
+        }
+        fn key(self, p: Point) -> Coord {
+            let (a, b) = self.coefficients();
+            p.row * a + p.col * b
+        }
+        // Where the point is along its line
+        fn pos(self, p: Point) -> Coord {
+            match self {
+                Self::Horizontal => p.col,
+                _ => p.row,
+            }
+        }
+        fn point(self, key: Coord, pos: Coord) -> Point {
+            match self {
+                Self::Horizontal => Point { row: key, col: pos },
+                Self::Vertical => Point { row: pos, col: key },
+                Self::Diagonal => Point {
+                    row: pos,
+                    col: pos - key,
+                },
+                Self::AntiDiagonal => Point {
+                    row: pos,
+                    col: key - pos,
+                },
+            }
+        }
+        // Range of the keys of `other`'s lines crossing the given interval of one of our lines
+        fn key_range(self, key: Coord, interval: (Coord, Coord), other: Self) -> (Coord, Coord) {
+            let start = other.key(self.point(key, interval.0));
+            let end = other.key(self.point(key, interval.1));
+            (start.min(end), start.max(end))
+        }
+    }
+
+    // Where the lines of two different directions cross, if it's on the grid
+    fn crossing(
+        direction: Direction,
+        key: Coord,
+        other: Direction,
+        other_key: Coord,
+    ) -> Option<Point> {
+        let (a1, b1) = direction.coefficients();
+        let (a2, b2) = other.coefficients();
+        let det = a1 * b2 - a2 * b1;
+        let row = key * b2 - other_key * b1;
+        let col = a1 * other_key - a2 * key;
+        if row % det != 0 || col % det != 0 {
+            None
+        } else {
+            Some(Point {
+                row: row / det,
+                col: col / det,
+            })
+        }
+    }
+
+    // Inclusive intervals of positions along a line, sorted and disjoint
+    struct Line {
+        key: Coord,
+        covered: Vec<(Coord, Coord)>,
+        doubly_covered: Vec<(Coord, Coord)>,
+    }
+
+    fn push_merged(intervals: &mut Vec<(Coord, Coord)>, interval: (Coord, Coord)) {
+        match intervals.last_mut() {
+            Some(last) if last.1 + 1 == interval.0 => last.1 = interval.1,
+            _ => intervals.push(interval),
+        }
+    }
+
+    // Sorted by key
+    fn lines(segments: &[Segment], direction: Direction) -> Vec<Line> {
+        let mut intervals_by_key = BTreeMap::<Coord, Vec<(Coord, Coord)>>::new();
         for segment in segments {
-            let mut last_segment = None;
-            match mapping_mode {
-                MappingMode::Orthogonal => {
-                    for shorter_segment in OrthogonalSegmentIterator(*segment) {
-                        last_segment = Some(shorter_segment);
-                        data[row_major_index(shorter_segment.start, n_cols)] += 1;
+            if Direction::of(segment) == Some(direction) {
+                let (start, end) = (segment.start.into(), segment.end.into());
+                let key = direction.key(start);
+                let (start, end) = (direction.pos(start), direction.pos(end));
+                intervals_by_key
+                    .entry(key)
+                    .or_default()
+                    .push((start.min(end), start.max(end)));
+            }
+        }
+        intervals_by_key
+            .into_iter()
+            .map(|(key, intervals)| {
+                let mut events = intervals
+                    .iter()
+                    .flat_map(|(start, end)| [(*start, 1), (*end + 1, -1)])
+                    .collect::<Vec<_>>();
+                events.sort_unstable();
+                let mut line = Line {
+                    key,
+                    covered: Vec::new(),
+                    doubly_covered: Vec::new(),
+                };
+                let mut depth = 0;
+                for (index, (pos, delta)) in events.iter().enumerate() {
+                    depth += delta;
+                    match events.get(index + 1) {
+                        // Wait for the last event at this position
+                        Some((next_pos, _)) if next_pos == pos => {}
+                        Some((next_pos, _)) => {
+                            let interval = (*pos, next_pos - 1);
+                            if depth >= 1 {
+                                push_merged(&mut line.covered, interval);
+                            }
+                            if depth >= 2 {
+                                push_merged(&mut line.doubly_covered, interval);
+                            }
+                        }
+                        None => {}
                     }
                 }
-                MappingMode::OrthogonalOrDiagonal => {
-                    for shorter_segment in OrthogonalOrDiagonalSegmentIterator(*segment) {
-                        last_segment = Some(shorter_segment);
-                        data[row_major_index(shorter_segment.start, n_cols)] += 1;
+                line
+            })
+            .collect()
+    }
+
+    fn is_doubly_covered(lines: &[Line], direction: Direction, p: Point) -> bool {
+        let (key, pos) = (direction.key(p), direction.pos(p));
+        lines
+            .binary_search_by_key(&key, |line| line.key)
+            .is_ok_and(|index| {
+                let intervals = &lines[index].doubly_covered;
+                let next = intervals.partition_point(|(start, _)| *start <= pos);
+                next > 0 && intervals[next - 1].1 >= pos
+            })
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum EventKind {
+        // At the same key, lines start before they are queried, and end after
+        Start,
+        Query,
+        End,
+    }
+
+    // Sweep over the keys of `direction`, with the lines of `other` that cross them
+    fn crossings(
+        (direction, lines): (Direction, &[Line]),
+        (other, other_lines): (Direction, &[Line]),
+        points: &mut Vec<Point>,
+    ) {
+        let mut events = Vec::new();
+        for line in other_lines {
+            for interval in &line.covered {
+                let (start, end) = other.key_range(line.key, *interval, direction);
+                events.push((start, EventKind::Start, line.key, line.key));
+                events.push((end, EventKind::End, line.key, line.key));
+            }
+        }
+        for line in lines {
+            for interval in &line.covered {
+                let (start, end) = direction.key_range(line.key, *interval, other);
+                events.push((line.key, EventKind::Query, start, end));
+            }
+        }
+        events.sort_unstable();
+        let mut active = BTreeMap::<Coord, usize>::new();
+        for (key, kind, start, end) in events {
+            match kind {
+                EventKind::Start => *active.entry(start).or_insert(0) += 1,
+                EventKind::End => {
+                    let count = active.get_mut(&start).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        active.remove(&start);
                     }
                 }
-            };
-            if let Some(last_segment) = last_segment {
-                data[row_major_index(last_segment.end, n_cols)] += 1;
+                EventKind::Query => points.extend(
+                    active
+                        .range(start..=end)
+                        .filter_map(|(other_key, _)| crossing(direction, key, other, *other_key)),
+                ),
             }
         }
-        Self { data }
     }
-    fn count_gt_one(&self) -> usize {
-        self.data
+
+    pub(super) fn count_overlaps(segments: &[Segment], mapping_mode: MappingMode) -> usize {
+        let directions: &[Direction] = match mapping_mode {
+            MappingMode::Orthogonal => &[Direction::Horizontal, Direction::Vertical],
+            MappingMode::OrthogonalOrDiagonal => &[
+                Direction::Horizontal,
+                Direction::Vertical,
+                Direction::Diagonal,
+                Direction::AntiDiagonal,
+            ],
+        };
+        let lines = directions
             .iter()
-            .map(|value| if *value > 1 { 1 } else { 0 })
-            .sum()
+            .map(|direction| lines(segments, *direction))
+            .collect::<Vec<_>>();
+        let doubly_covered = lines
+            .iter()
+            .flatten()
+            .flat_map(|line| &line.doubly_covered)
+            .map(|(start, end)| (end - start + 1) as u128)
+            .sum::<u128>();
+        let mut points = Vec::new();
+        for i in 0..directions.len() {
+            for j in i + 1..directions.len() {
+                crossings(
+                    (directions[i], &lines[i]),
+                    (directions[j], &lines[j]),
+                    &mut points,
+                );
+            }
+        }
+        // Three or four directions may cross at the same point
+        points.sort_unstable_by_key(|p| (p.row, p.col));
+        points.dedup();
+        // A crossing that is also doubly covered in some directions was already counted for each
+        let count = points
+            .iter()
+            .map(|p| {
+                directions
+                    .iter()
+                    .zip(&lines)
+                    .filter(|(direction, lines)| is_doubly_covered(lines, **direction, *p))
+                    .count()
+            })
+            .fold(doubly_covered, |count, n_counted| {
+                count + 1 - n_counted as u128
+            });
+        count
+            .try_into()
+            .expect("More overlapping points than fit in a usize")
     }
 }
 
@@ -190,6 +579,16 @@ fn part2(data: &[Segment]) -> usize {
     g.count_gt_one()
 }
 
+#[aoc(day05, part1, Sweep)]
+fn part1_sweep(data: &[Segment]) -> usize {
+    sweep::count_overlaps(data, MappingMode::Orthogonal)
+}
+
+#[aoc(day05, part2, Sweep)]
+fn part2_sweep(data: &[Segment]) -> usize {
+    sweep::count_overlaps(data, MappingMode::OrthogonalOrDiagonal)
+}
+
 #[cfg(test)]
 mod tests {
     fn input() -> Vec<Segment> {
@@ -209,6 +608,7 @@ mod tests {
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::random::Random;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 5)
@@ -225,4 +625,165 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 19374)
     }
+    #[test]
+    fn test_part1_sweep_given_example_input() {
+        assert_eq!(part1_sweep(&example_input()), 5)
+    }
+    #[test]
+    fn test_part1_sweep() {
+        assert_eq!(part1_sweep(&input()), 8350)
+    }
+    #[test]
+    fn test_part2_sweep_given_example_input() {
+        assert_eq!(part2_sweep(&example_input()), 12)
+    }
+    #[test]
+    fn test_part2_sweep() {
+        assert_eq!(part2_sweep(&input()), 19374)
+    }
+    fn translate(segments: &[Segment], offset: Point) -> Vec<Segment> {
+        let translate = |p: Point| Point {
+            row: p.row + offset.row,
+            col: p.col + offset.col,
+        };
+        segments
+            .iter()
+            .map(|segment| Segment {
+                start: translate(segment.start),
+                end: translate(segment.end),
+            })
+            .collect()
+    }
+    #[test]
+    fn test_storages_match() {
+        for data in [example_input(), input()] {
+            for storage in [Storage::Dense, Storage::Sparse] {
+                assert_eq!(
+                    Grid::with_storage(&data, MappingMode::Orthogonal, storage).count_gt_one(),
+                    part1(&data)
+                );
+                assert_eq!(
+                    Grid::with_storage(&data, MappingMode::OrthogonalOrDiagonal, storage)
+                        .count_gt_one(),
+                    part2(&data)
+                );
+            }
+        }
+        assert_eq!(Storage::for_segments(&input()), Storage::Dense)
+    }
+    #[test]
+    fn test_huge_and_negative_coordinates() {
+        let far = Point {
+            row: -3_000_000_000,
+            col: 1 << 40,
+        };
+        let data = translate(&example_input(), far);
+        assert_eq!(Storage::for_segments(&data), Storage::Dense);
+        assert_eq!((part1(&data), part2(&data)), (5, 12));
+        // The same segments, far from a single point at the origin
+        let mut data = translate(&example_input(), far);
+        data.push(Segment::from_str("0,0 -> 0,1").unwrap());
+        assert_eq!(Storage::for_segments(&data), Storage::Sparse);
+        assert_eq!((part1(&data), part2(&data)), (5, 12))
+    }
+    #[test]
+    fn test_segments_spanning_more_than_i64() {
+        // Neither diagonal nor orthogonal, and its rows differ by more than i64::MAX
+        let mut data = example_input();
+        data.push(Segment::from_str("-9223372036854775808,0 -> 1,3").unwrap());
+        assert_eq!((part1(&data), part2(&data)), (5, 12))
+    }
+    #[test]
+    fn test_sweep_given_coordinates_near_i64_bounds() {
+        let parse = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|s| Segment::from_str(s).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let mut data = parse(&[
+            "0,-9223372036854775808 -> 0,9223372036854775807",
+            // Overlaps the end of the first segment on 8 points
+            "0,9223372036854775807 -> 0,9223372036854775800",
+            // Crosses the first segment at (0, 5)
+            "-9223372036854775808,5 -> 9223372036854775807,5",
+        ]);
+        assert_eq!(part1_sweep(&data), 8 + 1);
+        data.extend(parse(&[
+            // Crosses the first segment at (0, 0), and the vertical one at (5, 5)
+            "-9223372036854775808,-9223372036854775808 -> 9223372036854775807,9223372036854775807",
+            // Lines whose keys don't fit in an i64, each overlapping itself on 2 points
+            "9223372036854775806,9223372036854775807 -> 9223372036854775807,9223372036854775806",
+            "9223372036854775807,9223372036854775806 -> 9223372036854775806,9223372036854775807",
+            "-9223372036854775808,9223372036854775806 -> -9223372036854775807,9223372036854775807",
+            "-9223372036854775807,9223372036854775807 -> -9223372036854775808,9223372036854775806",
+        ]));
+        assert_eq!(part2_sweep(&data), 8 + 1 + 2 + 2 + 2)
+    }
+    // Pseudo random segments in a small area, so they overlap a lot
+    fn generate_segments(seed: u64, n_segments: usize, size: i64) -> Vec<Segment> {
+        let mut random = Random::new(seed);
+        let mut next = move |n: i64| random.below(n as u64) as i64;
+        (0..n_segments)
+            .map(|_| {
+                let start = Point {
+                    row: next(size) - size / 2,
+                    col: next(size) - size / 2,
+                };
+                let length = next(size);
+                // Mostly horizontal, vertical and diagonal, with some other ones that don't count
+                let (rows, cols) = match next(9) {
+                    0 => (0, length),
+                    1 => (0, -length),
+                    2 => (length, 0),
+                    3 => (-length, 0),
+                    4 => (length, length),
+                    5 => (-length, length),
+                    6 => (length, -length),
+                    7 => (-length, -length),
+                    _ => (next(size), next(size)),
+                };
+                Segment {
+                    start,
+                    end: Point {
+                        row: start.row + rows,
+                        col: start.col + cols,
+                    },
+                }
+            })
+            .collect()
+    }
+    #[test]
+    fn test_sweep_matches_grid_given_generated_segments() {
+        for (seed, n_segments, size) in [(1, 2, 5), (2, 10, 8), (3, 50, 20), (4, 300, 40)] {
+            for seed in seed * 100..seed * 100 + 20 {
+                let data = generate_segments(seed, n_segments, size);
+                assert_eq!(part1_sweep(&data), part1(&data), "{:?}", data);
+                assert_eq!(part2_sweep(&data), part2(&data), "{:?}", data);
+            }
+        }
+    }
+    #[test]
+    fn test_sweep_given_long_segments() {
+        let trillion: i64 = 1_000_000_000_000;
+        let data = [
+            format!("0,0 -> 0,{}", 2 * trillion),
+            format!("0,{} -> 0,{}", trillion, 3 * trillion),
+            // Crosses the overlap, so it's already counted
+            format!("-5,{} -> 5,{}", trillion, trillion),
+            // Crosses the first segment at (0, 5)
+            "-5,5 -> 5,5".to_string(),
+            // Both cross the first segment at (0, 0), then the one above at (5, 5) and (-5, 5)
+            format!("-10,-10 -> {},{}", trillion, trillion),
+            "10,-10 -> -10,10".to_string(),
+            // Crosses between grid points
+            "10,2 -> 11,3".to_string(),
+            "11,2 -> 10,3".to_string(),
+        ]
+        .iter()
+        .map(|s| Segment::from_str(s).unwrap())
+        .collect::<Vec<_>>();
+        assert_eq!(part1_sweep(&data), trillion as usize + 1 + 1);
+        assert_eq!(part2_sweep(&data), trillion as usize + 1 + 1 + 3)
+    }
 }
@@ -1,3 +1,4 @@
+use crate::simulation::Simulation;
 use aoc_runner_derive::{aoc, aoc_generator};
 
 #[derive(Clone)]
@@ -33,10 +34,18 @@ fn simulate_one_day(counts: &mut Counts) {
     counts.0[8] = count_newborns;
 }
 
-fn simulate_n_days(mut counts: Counts, n: usize) -> usize {
-    for _ in 0..n {
-        simulate_one_day(&mut counts);
+impl Iterator for Counts {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        simulate_one_day(self);
+        Some(self.0.iter().sum())
     }
+}
+
+impl Simulation for Counts {}
+
+fn simulate_n_days(mut counts: Counts, n: usize) -> usize {
+    (0..n).for_each(|_| simulate_one_day(&mut counts));
     counts.0.iter().sum()
 }
 
@@ -76,4 +85,23 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 1644286074024)
     }
+    #[test]
+    fn test_simulate_n_days_given_example_input() {
+        assert_eq!(simulate_n_days(example_input(), 0), 5);
+        assert_eq!(simulate_n_days(example_input(), 18), 26)
+    }
+    #[test]
+    fn test_observe_given_example_input() {
+        let mut days = Vec::new();
+        example_input()
+            .observe(|counts| days.push(counts.0))
+            .take(3)
+            .for_each(drop);
+        // 2,3,2,0,1
+        assert_eq!(days[0], [1, 1, 2, 1, 0, 0, 0, 0, 0]);
+        // 1,2,1,6,0,8
+        assert_eq!(days[1], [1, 2, 1, 0, 0, 0, 1, 0, 1]);
+        // 0,1,0,5,6,7,8
+        assert_eq!(days[2], [2, 1, 0, 0, 0, 1, 1, 1, 1])
+    }
 }
@@ -42,11 +42,16 @@ fn neighbours(index: usize) -> impl Iterator<Item = usize> {
 
 mod grid {
     use super::neighbours;
+    use crate::simulation::Simulation;
     pub(super) struct Octopi(Vec<u8>);
     impl Octopi {
         pub(super) fn from_data(data: &[u8]) -> Self {
             Self(data.to_vec())
         }
+        #[cfg(test)]
+        pub(super) fn energy_levels(&self) -> Vec<u8> {
+            self.0.clone()
+        }
     }
     impl Iterator for Octopi {
         type Item = usize;
@@ -84,10 +89,12 @@ mod grid {
             Some(have_flashed.len())
         }
     }
+    impl Simulation for Octopi {}
 }
 
 mod buckets {
     use super::neighbours;
+    use crate::simulation::Simulation;
 
     pub(super) struct Octopi {
         indices: Vec<bit_set::BitSet>,
@@ -104,6 +111,16 @@ mod buckets {
 
             Self { indices }
         }
+        #[cfg(test)]
+        pub(super) fn energy_levels(&self) -> Vec<u8> {
+            let mut levels = vec![0; self.indices.iter().map(|index| index.len()).sum()];
+            for (level, index) in self.indices.iter().enumerate() {
+                for octopus in index {
+                    levels[octopus] = level as u8;
+                }
+            }
+            levels
+        }
     }
 
     impl Iterator for Octopi {
@@ -138,6 +155,7 @@ mod buckets {
             Some(self.indices[0].len())
         }
     }
+    impl Simulation for Octopi {}
 }
 
 #[aoc(day11, part1, Grid)]
@@ -189,6 +207,7 @@ mod tests {
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::simulation::Simulation;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1_grid(&example_input()), 1656);
@@ -209,4 +228,46 @@ mod tests {
         assert_eq!(part2_grid(&input()), 364);
         assert_eq!(part2_buckets(&input()), 364)
     }
+    #[test]
+    fn test_observe_given_example_input() {
+        let after_step_1 = parse_input(
+            "6594254334
+3856965822
+6375667284
+7252447257
+7468496589
+5278635756
+3287952832
+7993992245
+5957959665
+6394862637",
+        );
+        let after_step_2 = parse_input(
+            "8807476555
+5089087054
+8597889608
+8485769600
+8700908800
+6600088989
+6800005943
+0000007456
+9000000876
+8700006848",
+        );
+        let mut grid_levels = Vec::new();
+        grid::Octopi::from_data(&example_input())
+            .observe(|octopi| grid_levels.push(octopi.energy_levels()))
+            .take(2)
+            .for_each(drop);
+        assert_eq!(
+            grid_levels,
+            vec![after_step_1.clone(), after_step_2.clone()]
+        );
+        let mut buckets_levels = Vec::new();
+        buckets::Octopi::from_data(&example_input())
+            .observe(|octopi| buckets_levels.push(octopi.energy_levels()))
+            .take(2)
+            .for_each(drop);
+        assert_eq!(buckets_levels, vec![after_step_1, after_step_2])
+    }
 }
@@ -1,5 +1,6 @@
+use crate::render::{Backend, Braille, Raster};
+use crate::simulation::Simulation;
 use aoc_runner_derive::{aoc, aoc_generator};
-use drawille;
 use std::str::FromStr;
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -72,20 +73,25 @@ impl FromStr for InstructionsSheet {
     }
 }
 
+impl Raster for InstructionsSheet {
+    fn n_cols(&self) -> usize {
+        self.dots.iter().map(|dot| dot.x).max().unwrap() + 1
+    }
+    fn n_rows(&self) -> usize {
+        self.dots.iter().map(|dot| dot.y).max().unwrap() + 1
+    }
+    fn shade(&self, x: usize, y: usize) -> u8 {
+        if self.dots.contains(&Point { x, y }) {
+            u8::MAX
+        } else {
+            0
+        }
+    }
+}
+
 impl std::fmt::Display for InstructionsSheet {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let n_cols = self.dots.iter().map(|dot| dot.x).max().unwrap() + 1;
-        let n_rows = self.dots.iter().map(|dot| dot.y).max().unwrap() + 1;
-        let x_offset = 15;
-        let mut canvas = drawille::Canvas::new(n_cols as u32 + x_offset, n_rows as u32);
-        (0..n_rows).for_each(|y| {
-            (0..n_cols).for_each(|x| {
-                if self.dots.contains(&Point { x, y }) {
-                    canvas.set(x_offset + x as u32, y as u32);
-                }
-            })
-        });
-        write!(f, "{}", canvas.frame())
+        write!(f, "{}", Braille { x_offset: 15 }.render(self))
     }
 }
 
@@ -132,6 +138,8 @@ impl Iterator for FoldIterator {
     }
 }
 
+impl Simulation for FoldIterator {}
+
 #[aoc_generator(day13)]
 fn parse_input(data: &str) -> InstructionsSheet {
     data.parse().unwrap()
@@ -179,6 +187,7 @@ fold along x=5";
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::snapshot::assert_snapshot;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 17)
@@ -188,17 +197,19 @@ fold along x=5";
         assert_eq!(part1(&input()), 810)
     }
     #[test]
+    fn test_observe_given_example_input() {
+        let mut n_dots = Vec::new();
+        FoldIterator::new(&example_input())
+            .observe(|folds| n_dots.push(folds.instructions.dots.len()))
+            .for_each(drop);
+        assert_eq!(n_dots, vec![17, 16])
+    }
+    #[test]
     fn test_part2_given_example_input() {
-        let output = "
-       ⢸⠉⢹ 
-       ⠈⠉⠉ ";
-        assert_eq!(&part2(&example_input()), output)
+        assert_snapshot("day13_part2_example", &part2(&example_input()))
     }
     #[test]
     fn test_part2() {
-        let output = "
-       ⢸⠤⡇⡇ ⢸⠭⡂⡇⢸⢸⠭⡂⡎⣑⢸⠭⠁⣏⡱ 
-       ⠘ ⠃⠓⠒⠘⠒⠁⠑⠊⠘⠒⠁⠑⠚⠘  ⠃⠑ ";
-        assert_eq!(&part2(&input()), output)
+        assert_snapshot("day13_part2", &part2(&input()))
     }
 }
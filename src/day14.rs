@@ -1,3 +1,4 @@
+use crate::simulation::Simulation;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::str::FromStr;
 
@@ -115,6 +116,8 @@ impl Iterator for Polymerization {
     }
 }
 
+impl Simulation for Polymerization {}
+
 #[aoc_generator(day14)]
 fn parse_input(data: &str) -> Polymerization {
     data.parse().unwrap()
@@ -184,4 +187,17 @@ CN -> C";
     fn test_part2() {
         assert_eq!(part2(&input()), 3447389044530)
     }
+    #[test]
+    fn test_observe_given_example_input() {
+        let mut letters = Vec::new();
+        example_input()
+            .observe(|polymerization| letters.push(polymerization.polymer_letters.clone()))
+            .take(2)
+            .for_each(drop);
+        // Letters are sorted: B, C, H, N
+        // NCNBCHB
+        assert_eq!(letters[0], vec![2, 2, 1, 2]);
+        // NBCCNBBBCBHCB
+        assert_eq!(letters[1], vec![6, 4, 1, 2])
+    }
 }
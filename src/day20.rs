@@ -1,5 +1,6 @@
+use crate::render::Raster;
+use crate::simulation::Simulation;
 use aoc_runner_derive::{aoc, aoc_generator};
-use drawille;
 use std::str::FromStr;
 
 const NINE_BITS_MASK: usize = 0b111111111;
@@ -98,29 +99,34 @@ impl Input {
     fn num_lit_pixels(&self) -> usize {
         self.image.iter().filter(|&px| *px).count()
     }
-    #[allow(unused)]
-    fn draw(self) -> Self {
-        let mut canvas = drawille::Canvas::new(self.n_cols as u32, self.n_rows() as u32);
-        (0..self.n_rows()).for_each(|x| {
-            (0..self.n_cols).for_each(|y| {
-                if self.image
-                    [row_first_index(self.n_cols as isize, x as isize, y as isize) as usize]
-                {
-                    canvas.set(y as u32, x as u32);
-                }
-            })
-        });
-        println!(
-            "{} * {} == {}",
-            self.n_cols,
-            self.n_rows(),
-            self.image.len()
-        );
-        println!("{}", canvas.frame());
-        self
+}
+
+impl Raster for Input {
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    fn n_rows(&self) -> usize {
+        self.n_rows()
+    }
+    fn shade(&self, x: usize, y: usize) -> u8 {
+        if self.image[row_first_index(self.n_cols as isize, y as isize, x as isize) as usize] {
+            u8::MAX
+        } else {
+            0
+        }
+    }
+}
+
+impl Iterator for Input {
+    type Item = usize;
+    fn next(&mut self) -> Option<Self::Item> {
+        *self = self.enhance();
+        Some(self.num_lit_pixels())
     }
 }
 
+impl Simulation for Input {}
+
 #[aoc_generator(day20)]
 fn parse_input(data: &str) -> Input {
     data.parse().unwrap()
@@ -128,16 +134,12 @@ fn parse_input(data: &str) -> Input {
 
 #[aoc(day20, part1)]
 fn part1(data: &Input) -> usize {
-    data.enhance().enhance().num_lit_pixels()
+    data.clone().nth(1).unwrap()
 }
 
 #[aoc(day20, part2)]
 fn part2(data: &Input) -> usize {
-    let mut d = data.clone();
-    for _ in 0..50 {
-        d = d.enhance()
-    }
-    d.num_lit_pixels()
+    data.clone().nth(49).unwrap()
 }
 
 #[cfg(test)]
@@ -156,6 +158,7 @@ mod tests {
         parse_input(EXAMPLE_INPUT_STR)
     }
     use super::*;
+    use crate::snapshot::assert_snapshot;
     #[test]
     fn test_part1_given_example_input() {
         assert_eq!(part1(&example_input()), 35)
@@ -172,4 +175,30 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(&input()), 16665)
     }
+    #[test]
+    fn test_observe_given_example_input() {
+        let mut n_cols = Vec::new();
+        let lit = example_input()
+            .observe(|input| n_cols.push(input.n_cols))
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(n_cols, vec![7, 9]);
+        assert_eq!(lit.last(), Some(&35))
+    }
+    #[test]
+    fn test_render_given_example_input() {
+        use crate::render::{Ascii, Backend};
+        let image = EXAMPLE_INPUT_STR.split_once("\n\n").unwrap().1;
+        assert_eq!(Ascii.render(&example_input()), image);
+        let mut enhanced = example_input();
+        enhanced.nth(1);
+        assert_snapshot("day20_example_step2", &Ascii.render(&enhanced))
+    }
+    #[test]
+    fn test_render_after_step2() {
+        use crate::render::{Backend, Braille};
+        let mut enhanced = input();
+        enhanced.nth(1);
+        assert_snapshot("day20_step2", &Braille::default().render(&enhanced))
+    }
 }
@@ -27,5 +27,12 @@ pub mod day21;
 //pub mod day23;
 //pub mod day24;
 //pub mod day25;
+#[cfg(test)]
+mod bench;
+mod random;
+pub mod render;
+pub mod simulation;
+#[cfg(test)]
+mod snapshot;
 
 aoc_lib! { year = 2021 }
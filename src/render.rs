@@ -0,0 +1,238 @@
+use drawille;
+use std::path::Path;
+
+/// A rectangular grid of cells that can be drawn by any `Backend`.
+/// Each cell has a shade between 0 (off) and 255 (fully on).
+pub trait Raster {
+    fn n_cols(&self) -> usize;
+    fn n_rows(&self) -> usize;
+    fn shade(&self, x: usize, y: usize) -> u8;
+    // Backends that can only draw two colours consider a cell lit past half intensity
+    fn lit(&self, x: usize, y: usize) -> bool {
+        self.shade(x, y) > u8::MAX / 2
+    }
+}
+
+/// A row major grid of booleans
+pub struct BoolGrid<'a> {
+    cells: &'a [bool],
+    n_cols: usize,
+}
+
+impl<'a> BoolGrid<'a> {
+    pub fn new(cells: &'a [bool], n_cols: usize) -> Self {
+        Self { cells, n_cols }
+    }
+}
+
+impl Raster for BoolGrid<'_> {
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    fn n_rows(&self) -> usize {
+        self.cells.len() / self.n_cols
+    }
+    fn shade(&self, x: usize, y: usize) -> u8 {
+        if self.cells[y * self.n_cols + x] {
+            u8::MAX
+        } else {
+            0
+        }
+    }
+}
+
+/// A row major grid of levels, where the highest level is drawn fully on
+pub struct HeatGrid<'a> {
+    cells: &'a [u8],
+    n_cols: usize,
+    max: u8,
+}
+
+impl<'a> HeatGrid<'a> {
+    pub fn new(cells: &'a [u8], n_cols: usize) -> Self {
+        Self {
+            cells,
+            n_cols,
+            max: cells.iter().copied().max().unwrap_or(0).max(1),
+        }
+    }
+}
+
+impl Raster for HeatGrid<'_> {
+    fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+    fn n_rows(&self) -> usize {
+        self.cells.len() / self.n_cols
+    }
+    fn shade(&self, x: usize, y: usize) -> u8 {
+        (self.cells[y * self.n_cols + x] as usize * u8::MAX as usize / self.max as usize) as u8
+    }
+}
+
+pub trait Backend {
+    fn render(&self, raster: &dyn Raster) -> String;
+    fn save(&self, raster: &dyn Raster, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.render(raster))
+    }
+}
+
+/// Packs 2x4 cells per character using braille dots
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Braille {
+    // Blank cells drawn left of the image
+    pub x_offset: u32,
+}
+
+impl Backend for Braille {
+    fn render(&self, raster: &dyn Raster) -> String {
+        let mut canvas = drawille::Canvas::new(
+            raster.n_cols() as u32 + self.x_offset,
+            raster.n_rows() as u32,
+        );
+        (0..raster.n_rows()).for_each(|y| {
+            (0..raster.n_cols()).for_each(|x| {
+                if raster.lit(x, y) {
+                    canvas.set(self.x_offset + x as u32, y as u32);
+                }
+            })
+        });
+        canvas.frame()
+    }
+}
+
+/// One character per cell: '#' when lit and '.' otherwise
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Ascii;
+
+impl Backend for Ascii {
+    fn render(&self, raster: &dyn Raster) -> String {
+        (0..raster.n_rows())
+            .map(|y| {
+                (0..raster.n_cols())
+                    .map(|x| if raster.lit(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Plain (ASCII) portable bitmap, where lit cells are black
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Pbm;
+
+impl Backend for Pbm {
+    fn render(&self, raster: &dyn Raster) -> String {
+        let mut out = format!("P1\n{} {}\n", raster.n_cols(), raster.n_rows());
+        for y in 0..raster.n_rows() {
+            let row = (0..raster.n_cols())
+                .map(|x| if raster.lit(x, y) { "1" } else { "0" })
+                .collect::<Vec<_>>();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Plain (ASCII) portable pixmap in shades of grey, where lit cells are white
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Ppm;
+
+impl Backend for Ppm {
+    fn render(&self, raster: &dyn Raster) -> String {
+        let mut out = format!("P3\n{} {}\n{}\n", raster.n_cols(), raster.n_rows(), u8::MAX);
+        for y in 0..raster.n_rows() {
+            let row = (0..raster.n_cols())
+                .map(|x| {
+                    let shade = raster.shade(x, y);
+                    format!("{} {} {}", shade, shade, shade)
+                })
+                .collect::<Vec<_>>();
+            out.push_str(&row.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// One square per cell that isn't off, with an opacity matching its shade
+#[derive(Debug, Clone, Copy)]
+pub struct Svg {
+    pub cell_size: usize,
+}
+
+impl Default for Svg {
+    fn default() -> Self {
+        Self { cell_size: 10 }
+    }
+}
+
+impl Backend for Svg {
+    fn render(&self, raster: &dyn Raster) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            raster.n_cols() * self.cell_size,
+            raster.n_rows() * self.cell_size
+        );
+        for y in 0..raster.n_rows() {
+            for x in 0..raster.n_cols() {
+                let shade = raster.shade(x, y);
+                if shade > 0 {
+                    out.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill-opacity=\"{:.3}\"/>\n",
+                        x * self.cell_size,
+                        y * self.cell_size,
+                        self.cell_size,
+                        self.cell_size,
+                        shade as f32 / u8::MAX as f32
+                    ));
+                }
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const CELLS: [bool; 6] = [true, false, false, false, true, true];
+    fn bool_grid() -> BoolGrid<'static> {
+        BoolGrid::new(&CELLS, 3)
+    }
+    #[test]
+    fn test_ascii() {
+        assert_eq!(Ascii.render(&bool_grid()), "#..\n.##")
+    }
+    #[test]
+    fn test_pbm() {
+        assert_eq!(Pbm.render(&bool_grid()), "P1\n3 2\n1 0 0\n0 1 1\n")
+    }
+    #[test]
+    fn test_ppm_given_heat_grid() {
+        let grid = HeatGrid::new(&[0, 1, 2, 4], 2);
+        assert_eq!(
+            Ppm.render(&grid),
+            "P3\n2 2\n255\n0 0 0 63 63 63\n127 127 127 255 255 255\n"
+        );
+        assert_eq!(Ascii.render(&grid), "..\n.#")
+    }
+    #[test]
+    fn test_svg() {
+        let svg = Svg { cell_size: 1 }.render(&bool_grid());
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"3\" height=\"2\">")
+        );
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(
+            svg.contains("<rect x=\"2\" y=\"1\" width=\"1\" height=\"1\" fill-opacity=\"1.000\"/>")
+        );
+    }
+    #[test]
+    fn test_braille() {
+        assert_eq!(Braille::default().render(&bool_grid()), "⠑⠂")
+    }
+}
@@ -0,0 +1,56 @@
+/// A simulation that advances by one step on every call to `next`.
+/// Its state can be inspected between steps by attaching an observer.
+pub trait Simulation: Iterator + Sized {
+    /// Call `observer` with the simulation's state after every step
+    fn observe<F: FnMut(&Self)>(self, observer: F) -> Observed<Self, F> {
+        Observed {
+            simulation: self,
+            observer,
+        }
+    }
+}
+
+pub struct Observed<S, F> {
+    simulation: S,
+    observer: F,
+}
+
+impl<S, F> Observed<S, F> {
+    pub fn simulation(&self) -> &S {
+        &self.simulation
+    }
+}
+
+impl<S: Simulation, F: FnMut(&S)> Iterator for Observed<S, F> {
+    type Item = S::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.simulation.next()?;
+        (self.observer)(&self.simulation);
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    struct Counter(usize);
+    impl Iterator for Counter {
+        type Item = ();
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.0 < 3 {
+                self.0 += 1;
+                Some(())
+            } else {
+                None
+            }
+        }
+    }
+    impl Simulation for Counter {}
+    #[test]
+    fn test_observe_every_step() {
+        let mut states = Vec::new();
+        let steps = Counter(0).observe(|counter| states.push(counter.0)).count();
+        assert_eq!(steps, 3);
+        assert_eq!(states, vec![1, 2, 3])
+    }
+}
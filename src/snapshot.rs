@@ -0,0 +1,54 @@
+/*
+ * Golden file testing for textual and visual outputs.
+ *
+ * Each snapshot is stored in `snapshots/<name>.snap`.
+ * When an output doesn't match its snapshot, the new output is written next to
+ * it as `snapshots/<name>.snap.new` so the two can be compared, and the test fails.
+ * To accept the new outputs, run the tests again with `UPDATE_SNAPSHOTS=1`.
+ */
+use std::fs;
+use std::path::PathBuf;
+
+const UPDATE_VAR: &str = "UPDATE_SNAPSHOTS";
+
+fn snapshot_path(name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(format!("{}.{}", name, extension))
+}
+
+pub(crate) fn assert_snapshot(name: &str, actual: &str) {
+    let path = snapshot_path(name, "snap");
+    let new_path = snapshot_path(name, "snap.new");
+    // Files are saved with a trailing newline to be friendlier to text editors
+    let contents = format!("{}\n", actual);
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        let _ = fs::remove_file(&new_path);
+        return;
+    }
+    let expected = fs::read_to_string(&path).ok();
+    if expected.as_deref() == Some(contents.as_str()) {
+        let _ = fs::remove_file(&new_path);
+        return;
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&new_path, contents).unwrap();
+    match expected {
+        Some(expected) => panic!(
+            "Snapshot {} doesn't match.\nExpected:\n{}\nActual:\n{}\nReview {} and rerun with {}=1 to accept it",
+            name,
+            expected,
+            actual,
+            new_path.display(),
+            UPDATE_VAR
+        ),
+        None => panic!(
+            "Missing snapshot {}. Review {} and rerun with {}=1 to accept it",
+            name,
+            new_path.display(),
+            UPDATE_VAR
+        ),
+    }
+}